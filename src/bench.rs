@@ -0,0 +1,144 @@
+// Runs every stepping engine on the same random soups, checking they agree
+// generation by generation and timing each against the plain loop.
+
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use super::{parse_rule, rule_str, rule_step, Edges, Stepper, STEP_THREADS};
+use crate::generate;
+use crate::pool;
+use crate::rng::{self, Rng};
+use crate::soup::grid_hash;
+
+
+pub struct BenchOptions {
+    seeds: u64,
+    size: (usize, usize),
+    gens: u64,
+    live: i32,
+    birth: i32,
+    edges: Edges,
+    density: f64,
+    seed: u64,
+}
+
+// everything after 'bench' on the command line
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<BenchOptions, String> {
+    let mut options = BenchOptions {
+        seeds: 5, size: (256, 256), gens: 500, live: 2, birth: 3, edges: Edges::Dead, density: 0.3, seed: rng::time_seed(),
+    };
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        let bad = || format!("bad value '{}' for {}", value, arg);
+        match arg.as_str() {
+            "--seeds" => options.seeds = value.parse().ok().filter(|n| *n > 0).ok_or_else(bad)?,
+            "--size" => {
+                let (cols, rows) = value.split_once('x').ok_or_else(bad)?;
+                let size = (cols.parse().map_err(|_| bad())?, rows.parse().map_err(|_| bad())?);
+                if size.0 == 0 || size.1 == 0 {return Err(bad())}
+                options.size = size;
+            },
+            "--gens" => options.gens = value.parse().map_err(|_| bad())?,
+            "--rule" => (options.live, options.birth) = parse_rule(&value).ok_or_else(bad)?,
+            "--edges" => options.edges = Edges::parse(&value).ok_or_else(bad)?,
+            "--density" => options.density = value.parse().ok().filter(|p| (0. ..=1.).contains(p)).ok_or_else(bad)?,
+            "--seed" => options.seed = value.parse().map_err(|_| bad())?,
+            _ => return Err(format!("unknown bench flag {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+
+#[derive(Clone, Copy)]
+enum Engine {
+    // rule_step on one thread, what the others are measured against
+    Plain,
+    // rule_step split across every core
+    Threaded,
+    // Stepper, only looking around what changed
+    Sparse,
+}
+
+const ENGINES: [Engine; 3] = [Engine::Plain, Engine::Threaded, Engine::Sparse];
+
+impl Engine {
+    fn name(self) -> &'static str {
+        match self {
+            Engine::Plain => "plain",
+            Engine::Threaded => "threaded",
+            Engine::Sparse => "sparse",
+        }
+    }
+
+    // steps grid through options.gens generations, hashing each one to compare.
+    // The time doesn't count the hashing.
+    fn run(self, mut grid: Vec<Vec<bool>>, options: &BenchOptions) -> (Vec<u64>, Duration) {
+        STEP_THREADS.store(match self {
+            Engine::Threaded => pool::threads(),
+            _ => 1,
+        }, Ordering::Relaxed);
+        let mut stepper = Stepper::new();
+        let mut hashes = Vec::with_capacity(options.gens as usize);
+        let mut time = Duration::ZERO;
+        for generation in 0..options.gens {
+            let timer = Instant::now();
+            grid = match self {
+                Engine::Sparse => stepper.step(&grid, options.live, options.birth, options.edges, generation),
+                _ => rule_step(&grid, options.live, options.birth, options.edges, generation),
+            };
+            time += timer.elapsed();
+            hashes.push(grid_hash(&grid));
+        }
+        (hashes, time)
+    }
+}
+
+
+// where an engine first stepped somewhere the plain one didn't
+pub struct Mismatch {
+    engine: &'static str,
+    seed: u64,
+    generation: u64,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} differs from plain on seed {} at generation {}", self.engine, self.seed, self.generation)
+    }
+}
+
+
+pub fn run(options: BenchOptions) -> Result<(), Mismatch> {
+    let (cols, rows) = options.size;
+    println!("seeds: {}  size: {}x{}  gens: {}  rule: {}  edges: {}  density: {}  seed: {}",
+             options.seeds, cols, rows, options.gens, rule_str(options.live, options.birth),
+             options.edges, options.density, options.seed);
+
+    let mut times = [Duration::ZERO; ENGINES.len()];
+    let mut mismatch = None;
+    for seed in options.seed..options.seed.wrapping_add(options.seeds) {
+        let soup = generate::uniform(&mut Rng::new(seed), cols, rows, options.density);
+        let (expected, time) = Engine::Plain.run(soup.clone(), &options);
+        times[0] += time;
+        for (i, engine) in ENGINES.iter().enumerate().skip(1) {
+            let (hashes, time) = engine.run(soup.clone(), &options);
+            times[i] += time;
+            if let Some(generation) = hashes.iter().zip(&expected).position(|(a, b)| a != b) {
+                // the first one found is the one reported, the rest still get timed
+                mismatch.get_or_insert(Mismatch {engine: engine.name(), seed, generation: generation as u64 + 1});
+            }
+        }
+    }
+
+    let steps = (options.seeds * options.gens).max(1) as f64;
+    for (engine, time) in ENGINES.iter().zip(times) {
+        println!("{:>10}  {:>9.3}s  {:>8.1}us/step  {:.2}x",
+                 engine.name(), time.as_secs_f64(), time.as_secs_f64() * 1e6 / steps,
+                 times[0].as_secs_f64() / time.as_secs_f64().max(f64::EPSILON));
+    }
+    match mismatch {
+        Some(mismatch) => Err(mismatch),
+        None => Ok(()),
+    }
+}
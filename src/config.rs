@@ -0,0 +1,204 @@
+// The config file. Plain 'key = value' lines, '#' for comments, eg
+//
+//   alive = @
+//   dead =
+//   framerates = 1 5 15 30 60
+//   born_color = green
+//   die_color = 160
+//   bind = z w
+//   history_mb = 64
+//   max_size = 300x100
+//   help_at_start = no
+//   confirm = ask
+//   export_crop = 2
+//   restore_session = yes
+//   sign_saves = yes
+//
+// An empty char means a space. Colors are crossterm names or 0-255 ansi values.
+// 'bind = new old' makes the key 'new' do what 'old' does.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use crossterm::style::Color;
+
+use super::{valid_chars, BORN_COLOR, DIE_COLOR, DIFF_COLOR};
+
+
+// how keys that throw away the grid are confirmed
+#[derive(Clone, Copy, PartialEq)]
+pub enum Confirm {
+    // pressing the key again, like 'qq'
+    Twice,
+    // a y/n question in the toolbar
+    Ask,
+    Off,
+}
+
+
+pub struct Config {
+    pub ch_t: char,
+    pub ch_f: char,
+    pub framerates: Vec<f64>,
+    pub born_color: Color,
+    pub die_color: Color,
+    pub diff_color: Color,
+    // pressed key -> built in key
+    pub keys: HashMap<char, char>,
+    // memory for rewinding, in bytes
+    pub history_cap: usize,
+    // cols, rows the grid stops growing at on big terminals, to keep frames quick
+    pub max_size: Option<(usize, usize)>,
+    // open on the controls, or go straight to the grid
+    pub help_at_start: bool,
+    pub confirm: Confirm,
+    // crop pictures and GIFs to the live cells, with this many dead ones around them
+    pub export_crop: Option<usize>,
+    // carry on from where the last session quit when started without a pattern
+    pub restore_session: bool,
+    // add an 'edited with cursed_life' comment to saved patterns
+    pub sign_saves: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            ch_t: 'O',
+            ch_f: ' ',
+            framerates: vec![0.5, 1., 2., 5., 10., 15., 20., 30., 45., 60., 90., 120., 999.],
+            born_color: BORN_COLOR,
+            die_color: DIE_COLOR,
+            diff_color: DIFF_COLOR,
+            keys: HashMap::new(),
+            history_cap: 64 << 20,
+            max_size: None,
+            help_at_start: true,
+            confirm: Confirm::Twice,
+            export_crop: None,
+            restore_session: false,
+            sign_saves: false,
+        }
+    }
+}
+
+impl Config {
+    // swaps a rebound key for the one the game knows
+    pub fn remap(&self, event: Option<Event>) -> Option<Event> {
+        match event {
+            Some(Event::Key(KeyEvent{code: KeyCode::Char(c), modifiers})) => {
+                let c = self.keys.get(&c).copied().unwrap_or(c);
+                Some(Event::Key(KeyEvent{code: KeyCode::Char(c), modifiers}))
+            },
+            event => event,
+        }
+    }
+}
+
+
+// $XDG_CONFIG_HOME/cursed_life/config, falling back to ~/.config
+pub fn path() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()).map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(dir.join("cursed_life").join("config"))
+}
+
+
+fn parse_char(value: &str) -> Result<char, String> {
+    let mut chars = value.chars();
+    let c = match (chars.next(), chars.next()) {
+        (None, _) => ' ',
+        (Some(c), None) => c,
+        _ => return Err(format!("'{}' is more than one char", value)),
+    };
+    if valid_chars(c) {Ok(c)} else {Err(format!("'{}' can't be drawn", c))}
+}
+
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    value.parse::<u8>().map(Color::AnsiValue)
+        .or_else(|_| Color::try_from(value))
+        .map_err(|_| format!("unknown color '{}'", value))
+}
+
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "yes" | "true" | "on" => Ok(true),
+        "no" | "false" | "off" => Ok(false),
+        _ => Err(format!("expected yes or no, got '{}'", value)),
+    }
+}
+
+
+pub fn parse(text: &str) -> Result<Config, String> {
+    let mut config = Config::default();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {continue}
+        let err = |e: String| format!("line {}: {}", n + 1, e);
+        let mut kv = line.splitn(2, '=');
+        let key = kv.next().unwrap_or("").trim();
+        // chars are taken as written, so only trim the space after '='
+        let value = kv.next().ok_or_else(|| err(format!("expected '{} = value'", key)))?;
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match key {
+            "alive" => config.ch_t = parse_char(value).map_err(err)?,
+            "dead" => config.ch_f = parse_char(value).map_err(err)?,
+            "framerates" => {
+                config.framerates = value.split_whitespace()
+                    .map(|f| f.parse::<f64>().ok().filter(|f| *f > 0.).ok_or_else(|| format!("bad framerate '{}'", f)))
+                    .collect::<Result<_, _>>().map_err(err)?;
+                if config.framerates.is_empty() {return Err(err("no framerates".to_string()))}
+            },
+            "born_color" => config.born_color = parse_color(value.trim()).map_err(err)?,
+            "die_color" => config.die_color = parse_color(value.trim()).map_err(err)?,
+            "diff_color" => config.diff_color = parse_color(value.trim()).map_err(err)?,
+            "history_mb" => {
+                let mb: usize = value.trim().parse().map_err(|_| err(format!("bad size '{}'", value.trim())))?;
+                config.history_cap = mb << 20;
+            },
+            "max_size" => {
+                let bad = || err(format!("expected 'max_size = COLSxROWS', got '{}'", value.trim()));
+                let (cols, rows) = value.trim().split_once('x').ok_or_else(bad)?;
+                let size: (usize, usize) = (cols.parse().map_err(|_| bad())?, rows.parse().map_err(|_| bad())?);
+                if size.0 == 0 || size.1 == 0 {return Err(bad())}
+                config.max_size = Some(size);
+            },
+            "help_at_start" => config.help_at_start = parse_bool(value.trim()).map_err(err)?,
+            "restore_session" => config.restore_session = parse_bool(value.trim()).map_err(err)?,
+            "sign_saves" => config.sign_saves = parse_bool(value.trim()).map_err(err)?,
+            "confirm" => config.confirm = match value.trim() {
+                "twice" => Confirm::Twice,
+                "ask" => Confirm::Ask,
+                "off" => Confirm::Off,
+                v => return Err(err(format!("expected twice, ask or off, got '{}'", v))),
+            },
+            "export_crop" => config.export_crop = match value.trim() {
+                "off" => None,
+                v => Some(v.parse().map_err(|_| err(format!("expected a margin or off, got '{}'", v)))?),
+            },
+            "bind" => {
+                let keys: Vec<char> = value.split_whitespace().flat_map(str::chars).collect();
+                match keys[..] {
+                    [new, old] => {config.keys.insert(new, old);},
+                    _ => return Err(err(format!("expected 'bind = new old', got '{}'", value.trim()))),
+                }
+            },
+            _ => return Err(err(format!("unknown setting '{}'", key))),
+        }
+    }
+    if config.ch_t == config.ch_f {return Err("alive and dead chars are the same".to_string())}
+    Ok(config)
+}
+
+
+// a missing file is just the defaults
+pub fn load(path: &Path) -> Result<Config, String> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
@@ -0,0 +1,63 @@
+// Finding pattern files from inside the UI, for the ctrl-o open dialog and
+// tab completion when a path's typed in.
+
+use std::path::{Path, PathBuf};
+
+
+// what the dialog shows unless asked for everything. Sessions are RLE too.
+pub const EXTENSIONS: &[&str] = &["rle", "cells"];
+
+
+pub struct Entry {
+    pub name: String,
+    pub path: PathBuf,
+    pub dir: bool,
+}
+
+
+// a directory's contents, subdirectories first, each lot by name. Hidden files and
+// anything not a pattern are left out unless all is set.
+pub fn list(dir: &Path, all: bool) -> Result<Vec<Entry>, String> {
+    let read = std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let mut entries: Vec<Entry> = read.flatten().filter_map(|entry| {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        // following links, so a linked directory opens like any other
+        let dir = path.is_dir();
+        let pattern = path.extension().and_then(|e| e.to_str()).is_some_and(|e| EXTENSIONS.contains(&e));
+        if !all && (name.starts_with('.') || !(dir || pattern)) {return None}
+        Some(Entry {name, path, dir})
+    }).collect();
+    entries.sort_by(|a, b| b.dir.cmp(&a.dir).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+
+// fills in as much more of a typed path as everything it could be agrees on, with a '/'
+// after a directory. Left as typed when nothing matches.
+pub fn complete(typed: &str) -> String {
+    let (dir, prefix) = match typed.rfind('/') {
+        Some(i) => typed.split_at(i + 1),
+        None => ("", typed),
+    };
+    let read = match std::fs::read_dir(if dir.is_empty() {"."} else {dir}) {
+        Ok(read) => read,
+        Err(_) => return typed.to_string(),
+    };
+    let matches: Vec<String> = read.flatten().filter_map(|entry| {
+        let name = entry.file_name().into_string().ok()?;
+        // hidden ones only once a '.' is typed
+        if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {return None}
+        Some(if entry.path().is_dir() {name + "/"} else {name})
+    }).collect();
+    let first = match matches.first() {
+        Some(first) => first.as_str(),
+        None => return typed.to_string(),
+    };
+    let common = matches.iter().skip(1).fold(first, |common, name| {
+        let len = common.char_indices().zip(name.chars()).take_while(|((_, a), b)| a == b)
+            .last().map_or(0, |((i, a), _)| i + a.len_utf8());
+        &common[..len]
+    });
+    format!("{}{}", dir, common)
+}
@@ -0,0 +1,93 @@
+// Gamepads through the Linux joystick interface, /dev/input/jsN. A thread reads the
+// device and turns the pad into the keys it stands for, which get_event hands out
+// along with the terminal's. D-pad or left stick moves, A toggles, start plays.
+// Reading 8-byte events off a file keeps to std, where gilrs would bring its own
+// backends for every platform. Elsewhere --gamepad fails to open the device.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{mpsc, Mutex, OnceLock};
+use std::thread;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+
+// js_event types
+const BUTTON: u8 = 0x01;
+const AXIS: u8 = 0x02;
+// or'd in for the state sent on opening
+const INIT: u8 = 0x80;
+
+// how far an axis has to go to count as a press
+const THRESHOLD: i16 = 16384;
+
+static EVENTS: OnceLock<Mutex<mpsc::Receiver<Event>>> = OnceLock::new();
+
+
+// button numbers as xpad and most PlayStation drivers report them
+fn button_key(number: u8) -> Option<char> {
+    match number {
+        // A or cross
+        0 => Some(' '),
+        // start, xpad then others
+        7 | 9 => Some('f'),
+        _ => None,
+    }
+}
+
+
+// the left stick is axes 0 and 1, the d-pad usually 6 and 7
+fn axis_key(number: u8, direction: i8) -> Option<char> {
+    match (number, direction) {
+        (0 | 6, -1) => Some('a'),
+        (0 | 6, 1) => Some('d'),
+        (1 | 7, -1) => Some('w'),
+        (1 | 7, 1) => Some('s'),
+        _ => None,
+    }
+}
+
+
+pub fn open(path: &Path) -> Result<(), String> {
+    let mut device = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (send, receive) = mpsc::channel();
+    EVENTS.set(Mutex::new(receive)).map_err(|_| "a gamepad is already open".to_string())?;
+    thread::spawn(move || {
+        // which way each axis is held, so holding one is a single press
+        let mut held = [0i8; 256];
+        let mut buf = [0u8; 8];
+        while device.read_exact(&mut buf).is_ok() {
+            // u32 time, i16 value, u8 type, u8 number
+            let value = i16::from_ne_bytes([buf[4], buf[5]]);
+            let (kind, number) = (buf[6], buf[7]);
+            if kind & INIT != 0 {continue}
+            let key = match kind {
+                BUTTON if value == 1 => button_key(number),
+                AXIS => {
+                    let direction = if value > THRESHOLD {1} else if value < -THRESHOLD {-1} else {0};
+                    let pressed = direction != 0 && direction != held[number as usize];
+                    held[number as usize] = direction;
+                    if pressed {axis_key(number, direction)} else {None}
+                },
+                _ => None,
+            };
+            if let Some(c) = key {
+                let event = Event::Key(KeyEvent{code: KeyCode::Char(c), modifiers: KeyModifiers::NONE});
+                if send.send(event).is_err() {break}
+            }
+        }
+    });
+    Ok(())
+}
+
+
+pub fn connected() -> bool {
+    EVENTS.get().is_some()
+}
+
+
+// a key from the pad, if one's waiting
+pub fn next() -> Option<Event> {
+    EVENTS.get()?.lock().ok()?.try_recv().ok()
+}
@@ -0,0 +1,93 @@
+// Starting grids made from scratch, random or otherwise.
+
+use crate::rng::Rng;
+
+
+// every cell alive with the same chance
+pub fn uniform(rng: &mut Rng, cols: usize, rows: usize, density: f64) -> Vec<Vec<bool>> {
+    (0..rows).map(|_| (0..cols).map(|_| rng.chance(density)).collect()).collect()
+}
+
+
+// blobs. Value noise: random values on a lattice 'scale' cells apart, smoothly
+// blended between, alive wherever that comes out over a half.
+pub fn noise(rng: &mut Rng, cols: usize, rows: usize, scale: f64) -> Vec<Vec<bool>> {
+    let scale = scale.max(1.);
+    let lattice_cols = (cols as f64 / scale) as usize + 2;
+    let lattice_rows = (rows as f64 / scale) as usize + 2;
+    let lattice: Vec<Vec<f64>> = (0..lattice_rows).map(|_| (0..lattice_cols).map(|_| rng.next_f64()).collect()).collect();
+    let smooth = |t: f64| t * t * (3. - 2. * t);
+
+    (0..rows).map(|y| (0..cols).map(|x| {
+        let (fx, fy) = (x as f64 / scale, y as f64 / scale);
+        let (lx, ly) = (fx as usize, fy as usize);
+        let (tx, ty) = (smooth(fx.fract()), smooth(fy.fract()));
+        let top = lattice[ly][lx] * (1. - tx) + lattice[ly][lx + 1] * tx;
+        let bottom = lattice[ly + 1][lx] * (1. - tx) + lattice[ly + 1][lx + 1] * tx;
+        top * (1. - ty) + bottom * ty > 0.5
+    }).collect()).collect()
+}
+
+
+//// Regular patterns ////
+
+// every other row
+pub fn stripes(cols: usize, rows: usize) -> Vec<Vec<bool>> {
+    (0..rows).map(|y| vec![y % 2 == 0; cols]).collect()
+}
+
+
+pub fn checkerboard(cols: usize, rows: usize) -> Vec<Vec<bool>> {
+    (0..rows).map(|y| (0..cols).map(|x| (x + y) % 2 == 0).collect()).collect()
+}
+
+
+// lines running down to the left, 'gap' cells apart
+pub fn diagonals(cols: usize, rows: usize, gap: usize) -> Vec<Vec<bool>> {
+    (0..rows).map(|y| (0..cols).map(|x| (x + y) % (gap + 1) == 0).collect()).collect()
+}
+
+
+// distance of each cell from the middle
+fn radii(cols: usize, rows: usize) -> impl Iterator<Item = impl Iterator<Item = f64>> {
+    let (cx, cy) = ((cols as f64 - 1.) / 2., (rows as f64 - 1.) / 2.);
+    (0..rows).map(move |y| (0..cols).map(move |x| ((x as f64 - cx).powi(2) + (y as f64 - cy).powi(2)).sqrt()))
+}
+
+
+// a disc in the middle, with a radius a third of the smaller side
+pub fn circle(cols: usize, rows: usize) -> Vec<Vec<bool>> {
+    let radius = cols.min(rows) as f64 / 3.;
+    radii(cols, rows).map(|row| row.map(|r| r <= radius).collect()).collect()
+}
+
+
+// the outline of circle(), a cell thick
+pub fn ring(cols: usize, rows: usize) -> Vec<Vec<bool>> {
+    let radius = cols.min(rows) as f64 / 3.;
+    radii(cols, rows).map(|row| row.map(|r| (r - radius).abs() < 0.5).collect()).collect()
+}
+
+
+//// Shapes ////
+// filling the whole of a cols x rows box, for drawing between two corners
+
+pub fn rectangle(cols: usize, rows: usize, filled: bool) -> Vec<Vec<bool>> {
+    (0..rows).map(|y| (0..cols).map(|x| filled || x == 0 || y == 0 || x == cols - 1 || y == rows - 1).collect()).collect()
+}
+
+
+// the biggest ellipse that fits. Outlines are the cells with a dead neighbour
+// side on, so they stay joined up.
+pub fn ellipse(cols: usize, rows: usize, filled: bool) -> Vec<Vec<bool>> {
+    let (cx, cy) = ((cols as f64 - 1.) / 2., (rows as f64 - 1.) / 2.);
+    let (rx, ry) = (cols as f64 / 2., rows as f64 / 2.);
+    let disc: Vec<Vec<bool>> = (0..rows).map(|y| (0..cols).map(|x| {
+        ((x as f64 - cx) / rx).powi(2) + ((y as f64 - cy) / ry).powi(2) <= 1.
+    }).collect()).collect();
+    if filled {return disc}
+    let inside = |x: usize, y: usize| disc.get(y).and_then(|row| row.get(x)).copied().unwrap_or(false);
+    (0..rows).map(|y| (0..cols).map(|x| {
+        inside(x, y) && (x == 0 || y == 0 || !inside(x - 1, y) || !inside(x + 1, y) || !inside(x, y - 1) || !inside(x, y + 1))
+    }).collect()).collect()
+}
@@ -0,0 +1,215 @@
+// Running one pattern without the UI, for long experiments.
+// Writes checkpoints as it goes so a run can be picked back up later.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use super::{gen_grid, parse_rule, population, rule_str, stamp, Edges, Stepper, STEP_THREADS};
+use crate::pattern::{self, Pattern};
+use crate::soup::{grid_hash, PERIOD_WINDOW};
+
+
+// where checkpoints go when no --checkpoint is given
+pub const DEFAULT_CHECKPOINT: &str = "cursed_life.checkpoint.rle";
+
+// checkpoints keep what's past the edges in '#C checkpoint edges torus' lines, like session settings
+const EDGES_PREFIX: &str = "#C checkpoint edges ";
+
+// dead space added around a pattern when no --size is given
+const MARGIN: usize = 64;
+
+
+pub struct RunOptions {
+    path: PathBuf,
+    // a checkpoint to carry on from instead
+    resume: Option<PathBuf>,
+    gens: u64,
+    // from the pattern if not given
+    rule: Option<(i32, i32)>,
+    size: Option<(usize, usize)>,
+    // from the checkpoint when resuming if not given, otherwise dead
+    edges: Option<Edges>,
+    checkpoint_every: Option<u64>,
+    checkpoint: PathBuf,
+    out: Option<PathBuf>,
+    // print the results as JSON for scripts
+    json: bool,
+    // None to pick by grid size
+    step_threads: Option<usize>,
+}
+
+// everything after 'run' on the command line
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<RunOptions, String> {
+    let mut path = None;
+    let mut options = RunOptions {
+        path: PathBuf::new(), resume: None, gens: 10_000, rule: None, size: None, edges: None,
+        checkpoint_every: None, checkpoint: PathBuf::from(DEFAULT_CHECKPOINT), out: None, json: false,
+        step_threads: None,
+    };
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            path = Some(PathBuf::from(arg));
+            continue
+        }
+        if arg == "--json" {
+            options.json = true;
+            continue
+        }
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        let bad = || format!("bad value '{}' for {}", value, arg);
+        match arg.as_str() {
+            "--gens" => options.gens = value.parse().map_err(|_| bad())?,
+            "--rule" => options.rule = Some(parse_rule(&value).ok_or_else(bad)?),
+            "--size" => {
+                let (cols, rows) = value.split_once('x').ok_or_else(bad)?;
+                let size = (cols.parse().map_err(|_| bad())?, rows.parse().map_err(|_| bad())?);
+                if size.0 == 0 || size.1 == 0 {return Err(bad())}
+                options.size = Some(size);
+            },
+            "--edges" => options.edges = Some(Edges::parse(&value).ok_or_else(bad)?),
+            "--checkpoint-every" => options.checkpoint_every = Some(value.parse().ok().filter(|n| *n > 0).ok_or_else(bad)?),
+            "--checkpoint" => options.checkpoint = PathBuf::from(value),
+            "--resume" => options.resume = Some(PathBuf::from(value)),
+            "-o" | "--out" => options.out = Some(PathBuf::from(value)),
+            "--step-threads" => options.step_threads = Some(value.parse().ok().filter(|n| *n > 0).ok_or_else(bad)?),
+            _ => return Err(format!("unknown run flag {}", arg)),
+        }
+    }
+    options.path = match (path, &options.resume) {
+        (Some(_), Some(_)) => return Err("run takes a pattern file or --resume, not both".to_string()),
+        (None, None) => return Err("run needs a pattern file or --resume".to_string()),
+        (path, _) => path.unwrap_or_default(),
+    };
+    Ok(options)
+}
+
+
+// the whole grid, with the generation in the #CXRLE header. Written beside the old one
+// and moved over it, so being killed halfway never leaves a broken checkpoint.
+pub fn write_checkpoint(path: &Path, grid: &[Vec<bool>], generation: u64, live: i32, birth: i32, edges: Edges) -> Result<(), String> {
+    let mut pattern = Pattern::new(grid.to_vec(), Some(rule_str(live, birth)));
+    pattern.generation = Some(generation);
+    let text = format!("{}{}\n{}", EDGES_PREFIX, edges, pattern::to_rle(&pattern));
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    std::fs::write(&temp, text).and_then(|()| std::fs::rename(&temp, path))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+
+// the edges a checkpoint was written with, taking the line out so it isn't kept as a comment
+pub fn take_edges(pattern: &mut Pattern) -> Option<Edges> {
+    let edges = pattern.comments.iter().find_map(|c| Edges::parse(c.strip_prefix(EDGES_PREFIX)?.trim()));
+    pattern.comments.retain(|c| !c.starts_with(EDGES_PREFIX));
+    edges
+}
+
+
+// how a run ended
+pub enum Status {
+    DiedOut,
+    // repeating every so many generations, 1 for a still life
+    Settled(u64),
+    HitCap,
+}
+
+impl Status {
+    // for scripts to branch on. 1 is left for errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Status::Settled(_) => 0,
+            Status::DiedOut => 2,
+            Status::HitCap => 3,
+        }
+    }
+}
+
+
+pub fn run(options: RunOptions) -> Result<Status, String> {
+    STEP_THREADS.store(options.step_threads.unwrap_or(0), Ordering::Relaxed);
+    let (mut pattern, start) = match &options.resume {
+        Some(path) => {
+            let pattern = pattern::load(path)?;
+            let generation = pattern.generation
+                .ok_or_else(|| format!("{}: not a checkpoint, no #CXRLE generation", path.display()))?;
+            (pattern, generation)
+        },
+        None => (pattern::load(&options.path)?, 0),
+    };
+    let (live, birth) = match (options.rule, &pattern.rule) {
+        (Some(rule), _) => rule,
+        (None, Some(rule)) => parse_rule(rule).ok_or_else(|| format!("rule {} not supported", rule))?,
+        (None, None) => (2, 3),
+    };
+    let edges = options.edges.or(take_edges(&mut pattern)).unwrap_or(Edges::Dead);
+
+    // checkpoints are the whole grid already
+    let mut grid = if options.resume.is_some() {pattern.cells} else {
+        let (cols, rows) = options.size.unwrap_or((pattern.width() + MARGIN * 2, pattern.height() + MARGIN * 2));
+        let mut grid = gen_grid(cols, rows, None);
+        stamp(&mut grid, &pattern.cells,
+              (cols as isize - pattern.width() as isize) / 2,
+              (rows as isize - pattern.height() as isize) / 2);
+        grid
+    };
+    if grid.is_empty() {return Err("nothing to run, the grid is empty".to_string())}
+
+    let mut recent: VecDeque<u64> = VecDeque::with_capacity(PERIOD_WINDOW);
+    let mut period = None;
+    let mut generation = start;
+    let mut stepper = Stepper::new();
+    let timer = Instant::now();
+    while generation < options.gens {
+        let hash = grid_hash(&grid);
+        if let Some(i) = recent.iter().rev().position(|h| *h == hash) {
+            period = Some(i as u64 + 1);
+            break
+        }
+        if recent.len() == PERIOD_WINDOW {recent.pop_front();}
+        recent.push_back(hash);
+
+        grid = stepper.step(&grid, live, birth, edges, generation);
+        generation += 1;
+        if let Some(every) = options.checkpoint_every {
+            if generation.is_multiple_of(every) {
+                write_checkpoint(&options.checkpoint, &grid, generation, live, birth, edges)?;
+            }
+        }
+    }
+
+    let seconds = timer.elapsed().as_secs_f64();
+    let pop = population(&grid);
+    let status = match period {
+        _ if pop == 0 => Status::DiedOut,
+        Some(p) => Status::Settled(p),
+        None => Status::HitCap,
+    };
+
+    if options.json {
+        let (name, period) = match status {
+            Status::DiedOut => ("died out", None),
+            Status::Settled(p) => ("settled", Some(p)),
+            Status::HitCap => ("hit cap", None),
+        };
+        println!("{{\"generations\": {}, \"population\": {}, \"status\": \"{}\", \"period\": {}, \"seconds\": {:.3}}}",
+                 generation, pop, name, period.map_or("null".to_string(), |p| p.to_string()), seconds);
+    } else {
+        println!("generations: {}", generation);
+        println!("population: {}", pop);
+        match status {
+            Status::DiedOut => println!("status: died out"),
+            Status::Settled(1) => println!("status: still life"),
+            Status::Settled(p) => println!("status: settled with period {}", p),
+            Status::HitCap => println!("status: hit the {} generation cap", options.gens),
+        }
+        println!("time: {:.3}s", seconds);
+    }
+
+    if let Some(out) = &options.out {
+        pattern::save(out, &Pattern::new(grid, Some(rule_str(live, birth))))?;
+    }
+    Ok(status)
+}
@@ -0,0 +1,120 @@
+// Past generations for rewinding. Each step is kept as the cells that flipped
+// between generations rather than a whole grid, and the oldest steps are
+// dropped once the total passes a memory cap.
+
+use std::collections::VecDeque;
+
+
+pub struct History {
+    // indices of flipped cells, row-major, newest last
+    deltas: VecDeque<Vec<u32>>,
+    bytes: usize,
+    cap: usize,
+    // cols, rows of the grids the deltas were taken from
+    size: (usize, usize),
+}
+
+fn grid_size(grid: &[Vec<bool>]) -> (usize, usize) {
+    (grid.first().map_or(0, |row| row.len()), grid.len())
+}
+
+fn delta_bytes(delta: &[u32]) -> usize {
+    std::mem::size_of::<Vec<u32>>() + std::mem::size_of_val(delta)
+}
+
+impl History {
+    pub fn new(cap: usize) -> Self {
+        History {deltas: VecDeque::new(), bytes: 0, cap, size: (0, 0)}
+    }
+
+    pub fn set_cap(&mut self, cap: usize) {
+        self.cap = cap;
+        self.evict();
+    }
+
+    pub fn clear(&mut self) {
+        self.deltas.clear();
+        self.bytes = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    // roughly what's kept, as counted against the cap
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    // drop the oldest until under the cap
+    fn evict(&mut self) {
+        while self.bytes > self.cap {
+            match self.deltas.pop_front() {
+                Some(delta) => self.bytes -= delta_bytes(&delta),
+                None => break,
+            }
+        }
+    }
+
+    // remember a step from old to new
+    pub fn push(&mut self, old: &[Vec<bool>], new: &[Vec<bool>]) {
+        // deltas from a different sized grid don't line up
+        if grid_size(old) != self.size {
+            self.clear();
+            self.size = grid_size(old);
+        }
+        let width = self.size.0;
+        let delta: Vec<u32> = old.iter().zip(new).enumerate().flat_map(|(y, (old_row, new_row))| {
+            old_row.iter().zip(new_row).enumerate()
+                .filter(|(_, (a, b))| a != b)
+                .map(move |(x, _)| (y * width + x) as u32)
+        }).collect();
+        self.bytes += delta_bytes(&delta);
+        self.deltas.push_back(delta);
+        self.evict();
+    }
+
+    // flip the cells of one step, the newest being 0, to scrub back and forth without
+    // forgetting any. Flipping steps 0 to n-1 takes the grid back n generations and
+    // flipping them again brings it forward. false if there's no such step.
+    pub fn flip(&self, grid: &mut [Vec<bool>], step: usize) -> bool {
+        let delta = match self.deltas.len().checked_sub(step + 1).filter(|_| grid_size(grid) == self.size) {
+            Some(i) => &self.deltas[i],
+            None => return false,
+        };
+        let width = self.size.0;
+        for &i in delta {
+            let cell = &mut grid[i as usize / width][i as usize % width];
+            *cell = !*cell;
+        }
+        true
+    }
+
+    // forget the newest steps, once the grid's been flipped back past them
+    pub fn truncate(&mut self, steps: usize) {
+        for _ in 0..steps.min(self.deltas.len()) {
+            if let Some(delta) = self.deltas.pop_back() {self.bytes -= delta_bytes(&delta)}
+        }
+    }
+
+    // undo the newest step on the grid. false if there's none left.
+    // Edits made since are kept, as the flips are undone rather than the grid replaced.
+    pub fn back(&mut self, grid: &mut [Vec<bool>]) -> bool {
+        if grid_size(grid) != self.size {
+            self.clear();
+            return false
+        }
+        match self.deltas.pop_back() {
+            Some(delta) => {
+                self.bytes -= delta_bytes(&delta);
+                let width = self.size.0;
+                for i in delta {
+                    let cell = &mut grid[i as usize / width][i as usize % width];
+                    *cell = !*cell;
+                }
+                true
+            },
+            None => false,
+        }
+    }
+}
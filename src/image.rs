@@ -0,0 +1,237 @@
+// Pictures of the grid. PNGs are stored without compression, which keeps this
+// short and still comes out small enough for a clipboard. GIFs have to be LZW.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+
+// pixels a side for each cell
+pub const CELL_PX: usize = 4;
+
+pub const LIVE_RGB: [u8; 3] = [255, 255, 255];
+pub const DEAD_RGB: [u8; 3] = [0, 0, 0];
+
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {(crc >> 1) ^ 0xEDB8_8320} else {crc >> 1};
+        }
+    }
+    !crc
+}
+
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+
+// a zlib stream of stored deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+
+// the grid as an RGB PNG, CELL_PX pixels to a cell
+pub fn png(grid: &[Vec<bool>]) -> Vec<u8> {
+    let (cols, rows) = (grid.first().map_or(0, |row| row.len()), grid.len());
+    let (width, height) = (cols * CELL_PX, rows * CELL_PX);
+
+    // each scanline starts with its filter type, 0 for none
+    let mut pixels = Vec::with_capacity((width * 3 + 1) * height);
+    for row in grid {
+        let mut line = vec![0u8];
+        for alive in row {
+            let rgb = if *alive {LIVE_RGB} else {DEAD_RGB};
+            for _ in 0..CELL_PX {line.extend_from_slice(&rgb)}
+        }
+        for _ in 0..CELL_PX {pixels.extend_from_slice(&line)}
+    }
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut header = Vec::new();
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bit RGB, default compression and filters, no interlacing
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    chunk(&mut png, b"IHDR", &header);
+    chunk(&mut png, b"IDAT", &zlib_stored(&pixels));
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+
+// GIF's variable width LZW, for two colors
+fn lzw(pixels: &[u8]) -> Vec<u8> {
+    const MIN_SIZE: u32 = 2;
+    const CLEAR: u16 = 1 << MIN_SIZE;
+    const END: u16 = CLEAR + 1;
+    const MAX_CODE: u16 = 4096;
+
+    let mut out = vec![MIN_SIZE as u8];
+    let mut bytes = Vec::new();
+    let (mut bits, mut held) = (0u32, 0u32);
+    let mut emit = |code: u16, width: u32, bytes: &mut Vec<u8>| {
+        bits |= (code as u32) << held;
+        held += width;
+        while held >= 8 {
+            bytes.push(bits as u8);
+            bits >>= 8;
+            held -= 8;
+        }
+    };
+
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = END + 1;
+    let mut width = MIN_SIZE + 1;
+    emit(CLEAR, width, &mut bytes);
+    let mut pixels = pixels.iter();
+    if let Some(first) = pixels.next() {
+        let mut prefix = *first as u16;
+        for pixel in pixels {
+            if let Some(code) = table.get(&(prefix, *pixel)) {
+                prefix = *code;
+                continue
+            }
+            emit(prefix, width, &mut bytes);
+            table.insert((prefix, *pixel), next);
+            if next == 1 << width {width += 1}
+            next += 1;
+            // full, so start over
+            if next == MAX_CODE {
+                emit(CLEAR, width, &mut bytes);
+                table.clear();
+                next = END + 1;
+                width = MIN_SIZE + 1;
+            }
+            prefix = *pixel as u16;
+        }
+        emit(prefix, width, &mut bytes);
+    }
+    emit(END, width, &mut bytes);
+    emit(0, 7, &mut bytes);
+
+    // in sub-blocks of up to 255 bytes, then an empty one
+    for block in bytes.chunks(255) {
+        out.push(block.len() as u8);
+        out.extend_from_slice(block);
+    }
+    out.push(0);
+    out
+}
+
+
+// an animated GIF that loops, written a frame at a time so a long recording never has to
+// sit in memory. Every frame is cols x rows cells, CELL_PX pixels to a cell, with grids
+// cut or padded out to fit.
+pub struct Gif<W: Write> {
+    out: W,
+    cols: usize,
+    rows: usize,
+    frames: usize,
+}
+
+impl<W: Write> Gif<W> {
+    pub fn new(mut out: W, cols: usize, rows: usize) -> std::io::Result<Self> {
+        let (width, height) = ((cols * CELL_PX) as u16, (rows * CELL_PX) as u16);
+        out.write_all(b"GIF89a")?;
+        out.write_all(&width.to_le_bytes())?;
+        out.write_all(&height.to_le_bytes())?;
+        // a global table of 2 colors, background 0, square pixels
+        out.write_all(&[0xf0, 0, 0])?;
+        out.write_all(&DEAD_RGB)?;
+        out.write_all(&LIVE_RGB)?;
+        // loop forever
+        out.write_all(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+        Ok(Gif {out, cols, rows, frames: 0})
+    }
+
+    // a grid shown for delay hundredths of a second. Flushed straight away, so it's
+    // on disk even if we never get to finish().
+    pub fn frame(&mut self, grid: &[Vec<bool>], delay: u16) -> std::io::Result<()> {
+        let (width, height) = ((self.cols * CELL_PX) as u16, (self.rows * CELL_PX) as u16);
+        let mut gif = vec![0x21, 0xf9, 4, 0];
+        gif.extend_from_slice(&delay.to_le_bytes());
+        gif.extend_from_slice(&[0, 0]);
+        // the whole screen, no local colors or interlacing
+        gif.push(0x2c);
+        gif.extend_from_slice(&[0, 0, 0, 0]);
+        gif.extend_from_slice(&width.to_le_bytes());
+        gif.extend_from_slice(&height.to_le_bytes());
+        gif.push(0);
+
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..self.rows {
+            let mut line = Vec::with_capacity(width as usize);
+            for x in 0..self.cols {
+                let alive = grid.get(y).and_then(|row| row.get(x)).copied().unwrap_or(false);
+                line.extend_from_slice(&[alive as u8; CELL_PX]);
+            }
+            for _ in 0..CELL_PX {pixels.extend_from_slice(&line)}
+        }
+        gif.extend_from_slice(&lzw(&pixels));
+        self.out.write_all(&gif)?;
+        self.frames += 1;
+        self.out.flush()
+    }
+
+    // ends the file, returning how many frames went in
+    pub fn finish(mut self) -> std::io::Result<usize> {
+        self.out.write_all(&[0x3b])?;
+        self.out.flush()?;
+        Ok(self.frames)
+    }
+}
+
+
+// hands a PNG to whichever clipboard tool is installed. Terminals can only
+// take text, so there's no escape code to fall back on. false if none worked.
+pub fn copy_png(png: &[u8]) -> bool {
+    const TOOLS: &[(&str, &[&str])] = &[
+        ("wl-copy", &["--type", "image/png"]),
+        ("xclip", &["-selection", "clipboard", "-t", "image/png", "-i"]),
+    ];
+    TOOLS.iter().any(|(tool, args)| {
+        let child = Command::new(tool).args(*args)
+            .stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(_) => return false,
+        };
+        let written = child.stdin.take().is_some_and(|mut stdin| stdin.write_all(png).is_ok());
+        child.wait().is_ok_and(|status| status.success()) && written
+    })
+}
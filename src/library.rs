@@ -0,0 +1,49 @@
+// Patterns that come built in, for --demo and the 'O' browser.
+
+use crate::pattern::{self, Pattern};
+
+
+pub const PATTERNS: &[(&str, &str)] = &[
+    ("glider", "x = 3, y = 3\nbo$2bo$3o!"),
+    ("lwss", "x = 5, y = 4\nbo2bo$o$o3bo$4o!"),
+    ("r-pentomino", "x = 3, y = 3\nb2o$2o$bo!"),
+    ("acorn", "x = 7, y = 3\nbo$3bo$2o2b3o!"),
+    ("diehard", "x = 8, y = 3\n6bo$2o$bo3b3o!"),
+    ("pulsar", "x = 13, y = 13\n2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$\
+                o4bobo4bo$o4bobo4bo2$2b3o3b3o!"),
+    ("pentadecathlon", "x = 10, y = 3\n2bo4bo$2ob4ob2o$2bo4bo!"),
+    ("gosper-gun", "x = 36, y = 9\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+                    2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!"),
+];
+
+
+pub fn get(name: &str) -> Option<Pattern> {
+    let (name, rle) = PATTERNS.iter().find(|(n, _)| *n == name)?;
+    let mut pattern = pattern::parse_rle(rle).expect("built in patterns parse");
+    pattern.comments.push(format!("#N {}", name));
+    Some(pattern)
+}
+
+
+pub fn names() -> Vec<&'static str> {
+    PATTERNS.iter().map(|(name, _)| *name).collect()
+}
+
+
+// a small picture of some cells in half blocks, two rows to a line. Shrunk to fit
+// in cols x lines, with a spot alive if anything it covers is.
+pub fn thumbnail(cells: &[Vec<bool>], cols: usize, lines: usize) -> Vec<String> {
+    let (width, height) = (cells.first().map_or(0, |row| row.len()), cells.len());
+    let scale = width.div_ceil(cols.max(1)).max(height.div_ceil(lines.max(1) * 2)).max(1);
+    let alive = |x: usize, y: usize| (y * scale..((y + 1) * scale).min(height))
+        .any(|y| cells[y][(x * scale).min(width)..((x + 1) * scale).min(width)].contains(&true));
+    let (width, height) = (width.div_ceil(scale), height.div_ceil(scale));
+    (0..height.div_ceil(2)).map(|line| (0..width).map(|x| {
+        match (alive(x, line * 2), alive(x, line * 2 + 1)) {
+            (true, true) => '█',
+            (true, false) => '▀',
+            (false, true) => '▄',
+            (false, false) => ' ',
+        }
+    }).collect()).collect()
+}
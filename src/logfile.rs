@@ -0,0 +1,54 @@
+// A plain text log for --log-file, to see what a session did after the fact.
+// One line an entry: seconds since it opened, a kind and the details, eg
+//   12.034 event Key(KeyEvent { code: Char('f'), modifiers: NONE })
+//   12.051 span step 412us
+// Nothing's formatted or written unless a file was given.
+
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+
+static LOG: Mutex<Option<(File, Instant)>> = Mutex::new(None);
+
+
+// starts a fresh log, replacing whatever was in the file
+pub fn open(path: &Path) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    *LOG.lock().unwrap_or_else(|e| e.into_inner()) = Some((file, Instant::now()));
+    Ok(())
+}
+
+
+// a failed write is dropped, the log is never worth stopping for
+pub fn write(kind: &str, args: fmt::Arguments) {
+    if let Some((file, start)) = LOG.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        let _ = writeln!(file, "{:.3} {} {}", start.elapsed().as_secs_f64(), kind, args);
+    }
+}
+
+
+// logs how long it lived when dropped, for timing a stretch of code
+pub struct Span {
+    name: &'static str,
+    start: Instant,
+}
+
+pub fn span(name: &'static str) -> Span {
+    Span {name, start: Instant::now()}
+}
+
+impl Span {
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        write("span", format_args!("{} {}us", self.name, self.start.elapsed().as_micros()));
+    }
+}
@@ -1,144 +1,903 @@
+#![allow(clippy::four_forward_slashes)]
+
+use std::collections::HashMap;
 use std::convert::Into;
-use std::io::{Write, stdout};
+use std::fmt;
+use std::fmt::Write as FmtWrite;
+use std::io::{BufWriter, Write, stdout};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use crossterm::{
     ExecutableCommand, QueueableCommand,
     queue,
     cursor,
     event,
-    event::{Event, KeyEvent, KeyCode, MouseEvent, MouseButton, read, poll},
-    style::Print,
+    event::{Event, KeyEvent, KeyCode, KeyModifiers, MouseEvent, MouseButton, read, poll},
+    style::{Color, Print, StyledContent, Styler, style},
     terminal,
 };
 
+mod bench;
+mod config;
+use config::{Config, Confirm};
+mod gamepad;
+mod files;
+mod generate;
+mod headless;
+mod history;
+mod image;
+mod library;
+mod logfile;
+use history::History;
+mod pattern;
+use pattern::Pattern;
+mod pool;
+mod recent;
+mod render;
+mod rng;
+use rng::Rng;
+mod search;
+use search::Search;
+mod session;
+mod soup;
+mod undo;
+use undo::UndoTree;
+
+
+//// Errors ////
+
+// anything that ends the session early. Printed once the terminal is restored.
+#[derive(Debug)]
+enum Error {
+    Terminal(crossterm::ErrorKind),
+    TooSmall,
+    Pattern(String),
+    Usage(String),
+    // SIGTERM or SIGHUP
+    Terminated,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            // crossterm's own Display hides the io::Error, so dig it out
+            Error::Terminal(crossterm::ErrorKind::IoError(e)) => write!(f, "terminal error: {}", e),
+            Error::Terminal(e) => write!(f, "terminal error: {:?}", e),
+            Error::TooSmall => write!(f, "terminal too small"),
+            Error::Pattern(e) => write!(f, "failed to read pattern {}", e),
+            Error::Usage(e) => write!(f, "{}. See -h for usage", e),
+            Error::Terminated => write!(f, "closed by a signal"),
+        }
+    }
+}
+
+impl From<crossterm::ErrorKind> for Error {
+    fn from(e: crossterm::ErrorKind) -> Self {
+        Error::Terminal(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Terminal(e.into())
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
 
 //// Logic FNs ////
 
 // creates a new grid of x/y size optionally taking extra data from another grid
-fn gen_grid(cols: usize, rows: usize, grid: Option<Vec<Vec<bool>>>) ->  Vec<Vec<bool>> {
+fn gen_grid<T: Clone + Default>(cols: usize, rows: usize, grid: Option<Vec<Vec<T>>>) ->  Vec<Vec<T>> {
     match grid {
         Some(mut data) => {
             for col in &mut data{
-                col.resize(cols, false)
+                col.resize(cols, T::default())
             }
-            data.resize(rows, vec![false; cols]);
+            data.resize(rows, vec![T::default(); cols]);
             data
         }
-        None => vec![vec![false; cols]; rows],
+        None => vec![vec![T::default(); cols]; rows],
     }
 }
 
 
 // Toggles a point on the grid between true and false
-fn grid_toggle(grid: &mut Vec<Vec<bool>>, col: usize, row: usize) {
+fn grid_toggle(grid: &mut [Vec<bool>], col: usize, row: usize) {
     grid[row][col] = !grid[row][col]
 }
 
 
-// Returns a grid advanced one step in the GOL
-fn gol_step(grid: &Vec<Vec<bool>>, live: i32, birth: i32) -> Vec<Vec<bool>> {
-    // cast to i32's so subtractions don't panic.
-    // Unfortunately means recasting as usize later. Doesn't matter since get() bounds checks,
-    // and I strongly doubt someone has a screen size of a few billion tiles.
-    let max_x = grid[0].len() as i32;
-    let max_y = grid.len() as i32;
-
-    // returns a Vec<Vec<bool>>
-    (0..max_y).into_iter().map(|y| {
-        (0..max_x).into_iter().map(|x| {
-            let mut neighbors = 0;
-            // list of possible neighbors
-            let coords = [
-                [x, y - 1],  // up
-                [x + 1, y - 1],  // up right
-                [x + 1, y],  // right
-                [x + 1, y + 1],  // right down
-                [x, y + 1],  // down
-                [x - 1, y + 1],  // down left
-                [x - 1, y],  // left
-                [x - 1, y - 1],  // left up
-            ];
-
-            for point in coords.iter() {
-                // if the value underflows back to usize::max,
-                // it'll be out-of-bounds anyway
-                match grid.get(point[1] as usize) {
-                    Some(row) => {
-                        match row.get(point[0] as usize) {
-                            // Can't compare &true to true apparently.
-                            Some(val) => if val == &true {neighbors += 1;}
-                            None => (),
-                        }
-                    },
-                    None => (),
-                }
+// actual GOL logic. whether a cell is alive next step
+fn next_state(alive: bool, neighbors: i32, live: i32, birth: i32) -> bool {
+    neighbors == birth || (alive && neighbors >= live && neighbors < birth)
+}
+
+
+// what lies past the edges of the grid
+#[derive(Clone, Copy, PartialEq)]
+enum Edges {
+    // nothing, cells past the edge are always dead
+    Dead,
+    // opposite edges are joined
+    Torus,
+    // like the torus, but going over the top or bottom mirrors left to right
+    Klein,
+    // both pairs of edges joined mirrored, the real projective plane
+    Cross,
+    // like the torus, but going over the top or bottom shifts half the width along
+    Shifted,
+}
+
+impl Edges {
+    const ALL: [Edges; 5] = [Edges::Dead, Edges::Torus, Edges::Klein, Edges::Cross, Edges::Shifted];
+
+    fn parse(s: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|e| e.to_string() == s)
+    }
+
+    fn next(self) -> Self {
+        Self::ALL[(Self::ALL.iter().position(|e| *e == self).unwrap() + 1) % Self::ALL.len()]
+    }
+
+    // where a cell at x, y lands on a cols by rows grid, if anywhere.
+    // Only ever called a step past the edge, so the mirroring doesn't need to repeat.
+    fn map(self, x: i32, y: i32, cols: i32, rows: i32) -> Option<(usize, usize)> {
+        let (mut x, mut y) = (x, y);
+        if self == Edges::Dead {
+            if x < 0 || y < 0 || x >= cols || y >= rows {return None}
+            return Some((x as usize, y as usize))
+        }
+        if y < 0 || y >= rows {
+            y = y.rem_euclid(rows);
+            match self {
+                Edges::Klein | Edges::Cross => x = cols - 1 - x,
+                Edges::Shifted => x += cols / 2,
+                _ => (),
             }
+        }
+        if x < 0 || x >= cols {
+            // shifting can take x more than a step past, rem_euclid covers that too
+            x = x.rem_euclid(cols);
+            if self == Edges::Cross {y = rows - 1 - y}
+        }
+        Some((x as usize, y as usize))
+    }
+}
+
+impl fmt::Display for Edges {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            Edges::Dead => "dead",
+            Edges::Torus => "torus",
+            Edges::Klein => "klein",
+            Edges::Cross => "cross",
+            Edges::Shifted => "shifted",
+        })
+    }
+}
 
-            // actual GOL logic
-            if neighbors == birth {
-                true
-            } else if neighbors >= live && neighbors < birth && grid[y as usize][x as usize] {
-                true
-            } else {false}
+
+// up, up right, right, right down, down, down left, left, left up
+static NEIGHBORS: [(i32, i32); 8] = [(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1)];
+
+// the eight cells around x, y that are on the grid, after any wrapping
+fn neighbors(x: usize, y: usize, cols: usize, rows: usize, edges: Edges) -> impl Iterator<Item = (usize, usize)> {
+    let (x, y) = (x as i32, y as i32);
+    NEIGHBORS.iter().filter_map(move |&(dx, dy)| edges.map(x + dx, y + dy, cols as i32, rows as i32))
+}
+
+
+// threads full steps are split between, 0 to pick by grid size. Set once from --step-threads.
+static STEP_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+// builds each row of the next grid with row(y). Big grids are split into bands of rows,
+// one a thread in the pool. Small ones go quicker in a plain loop than handing them out.
+fn step_rows<F>(rows: usize, cells: usize, row: F) -> Vec<Vec<bool>> where
+    F: Fn(usize) -> Vec<bool> + Sync,
+{
+    let threads = match STEP_THREADS.load(Ordering::Relaxed) {
+        0 if cells < STEP_PARALLEL_CELLS => 1,
+        0 => pool::threads(),
+        n => n,
+    };
+    if threads == 1 || rows < 2 {
+        return (0..rows).map(row).collect()
+    }
+    let band = rows.div_ceil(threads);
+    let bands: Vec<Mutex<Vec<Vec<bool>>>> = (0..rows.div_ceil(band)).map(|_| Mutex::new(Vec::new())).collect();
+    pool::run(bands.len(), |i| {
+        *bands[i].lock().unwrap() = (i * band..rows.min((i + 1) * band)).map(&row).collect();
+    });
+    bands.into_iter().flat_map(|band| band.into_inner().unwrap()).collect()
+}
+
+
+// Returns a grid advanced one step in the GOL
+fn gol_step(grid: &[Vec<bool>], live: i32, birth: i32, edges: Edges) -> Vec<Vec<bool>> {
+    let (cols, rows) = (grid[0].len(), grid.len());
+    step_rows(rows, cols * rows, |y| {
+        (0..cols).map(|x| {
+            let neighbors = neighbors(x, y, cols, rows, edges).filter(|&(nx, ny)| grid[ny][nx]).count();
+            next_state(grid[y][x], neighbors as i32, live, birth)
         }).collect()
+    })
+}
+
+
+// gol_step, with B0 rules too. Those light up all of empty space every other generation,
+// past a dead edge as well, so on odd generations whatever's off the grid counts as alive.
+// The grid's always the real one, only drawing shows odd generations inverted.
+fn rule_step(grid: &[Vec<bool>], live: i32, birth: i32, edges: Edges, generation: u64) -> Vec<Vec<bool>> {
+    if birth != 0 {return gol_step(grid, live, birth, edges)}
+    let (cols, rows) = (grid[0].len(), grid.len());
+    let lit = generation % 2 == 1;
+    step_rows(rows, cols * rows, |y| {
+        (0..cols).map(|x| {
+            let (mut on_grid, mut alive) = (0, 0);
+            for (nx, ny) in neighbors(x, y, cols, rows, edges) {
+                on_grid += 1;
+                alive += grid[ny][nx] as i32;
+            }
+            if lit {alive += 8 - on_grid}
+            next_state(grid[y][x], alive, live, birth)
+        }).collect()
+    })
+}
+
+
+// B0 rules light up empty space on odd generations, so they're drawn inverted to stop everything strobing
+fn strobing(birth: i32, generation: u64) -> bool {
+    birth == 0 && generation % 2 == 1
+}
+
+
+// steps that only look around the cells that flipped last step, as nothing further
+// away can change either. Sparse patterns on big grids go a lot quicker.
+// Anything else that changes the grid between steps has to say so, with touch or forget.
+struct Stepper {
+    // the rules and size of the last step, None if the grid's changed wholesale since
+    last: Option<(i32, i32, Edges, usize, usize)>,
+    // cells flipped by the last step, and touched since
+    flipped: Vec<(usize, usize)>,
+}
+
+impl Stepper {
+    fn new() -> Self {
+        Stepper {last: None, flipped: Vec::new()}
+    }
+
+    // a cell changed by hand since the last step
+    fn touch(&mut self, x: usize, y: usize) {
+        self.flipped.push((x, y));
+    }
+
+    // the grid changed too much to keep track of, the next step looks at every cell
+    fn forget(&mut self) {
+        self.last = None;
+    }
+
+    // same as rule_step. Falls back to stepping every cell after forget, when the rules
+    // changed, or when so much flipped that sorting through it costs more than that.
+    fn step(&mut self, grid: &[Vec<bool>], live: i32, birth: i32, edges: Edges, generation: u64) -> Vec<Vec<bool>> {
+        let (cols, rows) = (grid[0].len(), grid.len());
+        // B0 rules flip everything empty, every step. And shifting half an odd width over
+        // the top and bottom isn't symmetric, so a cell's neighbors aren't all its neighbors' neighbors.
+        let lopsided = edges == Edges::Shifted && cols % 2 == 1;
+        let rules = Some((live, birth, edges, cols, rows));
+        let most = cols * rows / SPARSE_SHARE;
+        let next = if birth == 0 || lopsided || self.flipped.len() > most || self.last != rules {
+            let next = rule_step(grid, live, birth, edges, generation);
+            // stops counting once there's too many to be worth it
+            self.flipped = (0..rows).flat_map(|y| (0..cols).map(move |x| (x, y)))
+                .filter(|&(x, y)| grid[y][x] != next[y][x])
+                .take(most + 1)
+                .collect();
+            next
+        } else {
+            let mut around: Vec<(usize, usize)> = self.flipped.iter()
+                .flat_map(|&(x, y)| std::iter::once((x, y)).chain(neighbors(x, y, cols, rows, edges)))
+                .collect();
+            around.sort_unstable();
+            around.dedup();
+            let mut next = grid.to_vec();
+            self.flipped = around.into_iter().filter(|&(x, y)| {
+                let count = neighbors(x, y, cols, rows, edges).filter(|&(nx, ny)| grid[ny][nx]).count();
+                next[y][x] = next_state(grid[y][x], count as i32, live, birth);
+                next[y][x] != grid[y][x]
+            }).collect();
+            next
+        };
+        self.last = rules;
+        next
+    }
+}
+
+
+// two-species mode. which species each live cell of next belongs to, true for blue.
+// Survivors keep theirs and births take the majority of their parents, ties going to red.
+fn species_step(grid: &[Vec<bool>], species: &[Vec<bool>], next: &[Vec<bool>], edges: Edges) -> Vec<Vec<bool>> {
+    let (rows, cols) = (grid.len(), grid[0].len());
+    (0..rows).map(|y| (0..cols).map(|x| {
+        if !next[y][x] {return false}
+        if grid[y][x] {return species[y][x]}
+        let majority: i32 = neighbors(x, y, cols, rows, edges)
+            .filter(|&(nx, ny)| grid[ny][nx])
+            .map(|(nx, ny)| if species[ny][nx] {1} else {-1})
+            .sum();
+        majority > 0
+    }).collect()).collect()
+}
+
+
+// ages the trail left by dying cells between two generations
+fn age_trail(trail: &mut [Vec<u8>], old: &[Vec<bool>], new: &[Vec<bool>]) {
+    for (y, row) in trail.iter_mut().enumerate() {
+        for (x, age) in row.iter_mut().enumerate() {
+            *age = if new[y][x] {
+                0
+            } else if old[y][x] {
+                TRAIL_COLORS.len() as u8
+            } else {
+                age.saturating_sub(1)
+            };
+        }
+    }
+}
+
+
+// LifeHistory's record of which cells have ever been alive
+fn add_history(history: &mut [Vec<bool>], grid: &[Vec<bool>]) {
+    for (seen, alive) in history.iter_mut().flatten().zip(grid.iter().flatten()) {
+        *seen |= *alive;
+    }
+}
+
+
+// number of live cells
+fn population(grid: &[Vec<bool>]) -> usize {
+    grid.iter().map(|row| row.iter().filter(|c| **c).count()).sum()
+}
+
+
+// the live/birth rules written in the usual B/S notation.
+// Cells survive on anything from 'live' up to 'birth'.
+fn rule_str(live: i32, birth: i32) -> String {
+    let survive: String = (live..birth).chain(std::iter::once(birth)).map(|n| n.to_string()).collect();
+    format!("B{}/S{}", birth, survive)
+}
+
+
+// reads B/S notation, or the older S/B like 23/3, into live/birth rules.
+// None if the rule can't be expressed that way.
+fn parse_rule(rule: &str) -> Option<(i32, i32)> {
+    // Conway's rules with extra states to keep history, which we have our own way
+    if rule.trim().eq_ignore_ascii_case("LifeHistory") {return Some((2, 3))}
+    let digits = |s: &str| s.chars().map(|c| c.to_digit(10).map(|d| d as i32)).collect::<Option<Vec<i32>>>();
+    let mut parts = rule.trim().splitn(2, '/');
+    let (first, second) = (parts.next()?, parts.next()?);
+    let (born, survive) = match (first.chars().next(), second.chars().next()) {
+        (Some('B'), Some('S')) | (Some('b'), Some('s')) => (digits(&first[1..])?, digits(&second[1..])?),
+        (Some('S'), Some('B')) | (Some('s'), Some('b')) => (digits(&second[1..])?, digits(&first[1..])?),
+        _ => (digits(second)?, digits(first)?),
+    };
+
+    // one birth count, which cells also survive on, plus a run of survivals just below it
+    if born.len() != 1 {return None}
+    let birth = born[0];
+    if !survive.contains(&birth) {return None}
+    let mut below: Vec<i32> = survive.into_iter().filter(|n| *n != birth).collect();
+    below.sort_unstable();
+    below.dedup();
+    let live = below.first().copied().unwrap_or(birth);
+    if below == (live..birth).collect::<Vec<i32>>() {Some((live, birth))} else {None}
+}
+
+
+// copies the live cells of a pattern onto the grid with its top-left at col, row.
+// Anything that lands off the grid is dropped.
+fn stamp(grid: &mut [Vec<bool>], cells: &[Vec<bool>], col: isize, row: isize) {
+    for (y, pattern_row) in cells.iter().enumerate() {
+        for (x, alive) in pattern_row.iter().enumerate() {
+            let (gx, gy) = (col + x as isize, row + y as isize);
+            if !alive || gx < 0 || gy < 0 {continue}
+            if let Some(cell) = grid.get_mut(gy as usize).and_then(|r| r.get_mut(gx as usize)) {
+                *cell = true;
+            }
+        }
+    }
+}
+
+
+// where a loaded pattern's top-left goes on a grid. Where #CXRLE says, relative to the
+// middle like Golly, otherwise centered.
+fn pattern_origin(pattern: &Pattern, grid_cols: usize, grid_rows: usize) -> (isize, isize) {
+    match pattern.pos {
+        Some((x, y)) => ((grid_cols / 2) as isize + x as isize, (grid_rows / 2) as isize + y as isize),
+        None => ((grid_cols as isize - pattern.width() as isize) / 2,
+                 (grid_rows as isize - pattern.height() as isize) / 2),
+    }
+}
+
+
+// flips the cell and every one joined to it side on that's the same, like a paint bucket.
+// Fills dead space up to live walls, or clears a live shape. How many cells flipped.
+fn flood_fill(grid: &mut [Vec<bool>], col: usize, row: usize) -> usize {
+    let target = grid[row][col];
+    let mut todo = vec![(col, row)];
+    let mut filled = 0;
+    while let Some((x, y)) = todo.pop() {
+        if grid[y][x] != target {continue}
+        grid[y][x] = !target;
+        filled += 1;
+        if x > 0 {todo.push((x - 1, y))}
+        if y > 0 {todo.push((x, y - 1))}
+        if x + 1 < grid[y].len() {todo.push((x + 1, y))}
+        if y + 1 < grid.len() {todo.push((x, y + 1))}
+    }
+    filled
+}
+
+
+// the grid turned a quarter clockwise about its middle. Keeps its size, so on a grid
+// that isn't square whatever lands outside is lost and the gaps left are dead.
+fn rotate(grid: &[Vec<bool>]) -> Vec<Vec<bool>> {
+    let (cols, rows) = (grid.first().map_or(0, |row| row.len()) as i64, grid.len() as i64);
+    // doubled so the middle of an even side is on a whole number
+    let (cx, cy) = (cols - 1, rows - 1);
+    (0..rows).map(|y| (0..cols).map(|x| {
+        let (dx, dy) = (2 * x - cx, 2 * y - cy);
+        let (ox, oy) = ((dy + cx).div_euclid(2), (cy - dx).div_euclid(2));
+        (0..cols).contains(&ox) && (0..rows).contains(&oy) && grid[oy as usize][ox as usize]
+    }).collect()).collect()
+}
+
+
+// 'X' on a whole grid. h mirrors left to right, v top to bottom and r rotates clockwise.
+fn transform(grid: &[Vec<bool>], how: char) -> Vec<Vec<bool>> {
+    match how {
+        'h' => grid.iter().map(|row| row.iter().rev().copied().collect()).collect(),
+        'v' => grid.iter().rev().cloned().collect(),
+        _ => rotate(grid),
+    }
+}
+
+
+// where the cell at x, y goes when transform() moves it, if it's still on the grid
+fn transform_pos(x: usize, y: usize, cols: usize, rows: usize, how: char) -> Option<(usize, usize)> {
+    let (x, y, cols, rows) = (x as i64, y as i64, cols as i64, rows as i64);
+    let (nx, ny) = match how {
+        'h' => (cols - 1 - x, y),
+        'v' => (x, rows - 1 - y),
+        // rotate() backwards
+        _ => ((cols - 1 + rows - 1 - 2 * y).div_euclid(2), (2 * x - (cols - 1) + rows - 1).div_euclid(2)),
+    };
+    ((0..cols).contains(&nx) && (0..rows).contains(&ny)).then_some((nx as usize, ny as usize))
+}
+
+
+// each cell as a 2x2 block
+fn upscale(cells: &[Vec<bool>]) -> Vec<Vec<bool>> {
+    cells.iter().flat_map(|row| {
+        let row: Vec<bool> = row.iter().flat_map(|c| [*c, *c]).collect();
+        [row.clone(), row]
     }).collect()
 }
 
 
+// the cells on or inside a closed path, for the lasso. Jumps between points,
+// like from clicking, are joined with straight lines.
+fn lasso_mask(path: &[(usize, usize)], cols: usize, rows: usize) -> Vec<Vec<bool>> {
+    let mut mask = gen_grid(cols, rows, None);
+    let edges: Vec<((f64, f64), (f64, f64))> = path.iter().zip(path.iter().cycle().skip(1))
+        .map(|(a, b)| ((a.0 as f64, a.1 as f64), (b.0 as f64, b.1 as f64)))
+        .collect();
+    for ((ax, ay), (bx, by)) in &edges {
+        let steps = (bx - ax).abs().max((by - ay).abs()).max(1.);
+        for i in 0..=steps as usize {
+            let t = i as f64 / steps;
+            let (x, y) = ((ax + (bx - ax) * t).round() as usize, (ay + (by - ay) * t).round() as usize);
+            if let Some(cell) = mask.get_mut(y).and_then(|row| row.get_mut(x)) {*cell = true}
+        }
+    }
+    // even-odd, counting the edges crossed heading right from each cell
+    for (y, row) in mask.iter_mut().enumerate() {
+        let fy = y as f64;
+        for (x, cell) in row.iter_mut().enumerate() {
+            let crossings = edges.iter().filter(|((ax, ay), (bx, by))| {
+                (*ay > fy) != (*by > fy) && (x as f64) < ax + (fy - ay) * (bx - ax) / (by - ay)
+            }).count();
+            *cell |= crossings % 2 == 1;
+        }
+    }
+    mask
+}
+
+
+// (left, top, right, bottom) of the live cells, inclusive. None if there aren't any.
+fn bounding_box(grid: &[Vec<bool>]) -> Option<(usize, usize, usize, usize)> {
+    let mut bbox: Option<(usize, usize, usize, usize)> = None;
+    for (y, row) in grid.iter().enumerate() {
+        for (x, _) in row.iter().enumerate().filter(|(_, c)| **c) {
+            bbox = Some(match bbox {
+                Some((l, t, r, b)) => (l.min(x), t.min(y), r.max(x), b.max(y)),
+                None => (x, y, x, y),
+            });
+        }
+    }
+    bbox
+}
+
+
+// copies out the inclusive (left, top, right, bottom) region of a grid
+fn crop(grid: &[Vec<bool>], (left, top, right, bottom): (usize, usize, usize, usize)) -> Vec<Vec<bool>> {
+    grid[top..=bottom].iter().map(|row| row[left..=right].to_vec()).collect()
+}
+
+
+// a box to crop pictures to, and the margin of dead cells to keep around it
+type Crop = ((usize, usize, usize, usize), usize);
+
+
+// the region of a grid inside an inclusive (left, top, right, bottom) box, with margin dead
+// cells all round. Anything outside the grid is dead too, so it's fine if the box overhangs.
+fn crop_margin(grid: &[Vec<bool>], (left, top, right, bottom): (usize, usize, usize, usize), margin: usize) -> Vec<Vec<bool>> {
+    (0..bottom - top + 1 + margin * 2).map(|y| (0..right - left + 1 + margin * 2).map(|x| {
+        let (x, y) = ((x + left).checked_sub(margin), (y + top).checked_sub(margin));
+        x.zip(y).and_then(|(x, y)| grid.get(y)?.get(x).copied()).unwrap_or(false)
+    }).collect()).collect()
+}
+
+
+// a picture's worth of grid. With export_crop set only the live cells and a margin,
+// so big terminals don't come out mostly black. Empty grids are kept whole.
+fn export_view(grid: &[Vec<bool>], margin: Option<usize>) -> Vec<Vec<bool>> {
+    match margin.zip(bounding_box(grid)) {
+        Some((margin, bbox)) => crop_margin(grid, bbox, margin),
+        None => grid.to_vec(),
+    }
+}
+
+
+// a grid stepped alongside the main one under its own rules
+struct Layer {
+    cells: Vec<Vec<bool>>,
+    live: i32,
+    birth: i32,
+}
+
+
+// --inject. a pattern dropped in from the top or bottom edge every so many generations
+struct Inject {
+    every: u64,
+    cells: Vec<Vec<bool>>,
+    // from the top, otherwise the bottom
+    top: bool,
+}
+
+impl Inject {
+    // a glider heading se, sw, ne or nw, entering from the edge behind it
+    fn glider(every: u64, heading: &str) -> Option<Inject> {
+        let (south, east) = match heading {
+            "se" => (true, true),
+            "sw" => (true, false),
+            "ne" => (false, true),
+            "nw" => (false, false),
+            _ => return None,
+        };
+        // .O.
+        // ..O
+        // OOO heads south east
+        let mut cells = vec![vec![false, true, false], vec![false, false, true], vec![true, true, true]];
+        if !east {cells.iter_mut().for_each(|row| row.reverse())}
+        if !south {cells.reverse()}
+        Some(Inject {every, cells, top: south})
+    }
+
+    // stamps the pattern somewhere along its edge
+    fn drop(&self, grid: &mut [Vec<bool>], rng: &mut Rng) {
+        let (grid_cols, grid_rows) = (grid[0].len(), grid.len());
+        let (width, height) = (self.cells[0].len(), self.cells.len());
+        let col = rng.below(grid_cols.saturating_sub(width) as u64 + 1) as isize;
+        let row = if self.top {0} else {grid_rows as isize - height as isize};
+        stamp(grid, &self.cells, col, row);
+    }
+}
+
+
+// conditions that stop playback, set from the command line
+enum Trigger {
+    Above(usize),
+    Below(usize),
+    // a cell going from dead to alive
+    Cell(usize, usize),
+}
+
+impl Trigger {
+    fn parse(flag: &str, value: &str) -> Option<Trigger> {
+        match flag {
+            "--stop-above" => value.parse().ok().map(Trigger::Above),
+            "--stop-below" => value.parse().ok().map(Trigger::Below),
+            "--stop-cell" => {
+                let mut xy = value.splitn(2, ',');
+                Some(Trigger::Cell(xy.next()?.trim().parse().ok()?, xy.next()?.trim().parse().ok()?))
+            },
+            _ => None,
+        }
+    }
+
+    fn hit(&self, prev: &[Vec<bool>], grid: &[Vec<bool>], pop: usize) -> bool {
+        match *self {
+            Trigger::Above(n) => pop > n,
+            Trigger::Below(n) => pop < n,
+            Trigger::Cell(x, y) => grid.get(y).and_then(|r| r.get(x)) == Some(&true) &&
+                                   prev.get(y).and_then(|r| r.get(x)) != Some(&true),
+        }
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Trigger::Above(n) => write!(f, "population above {}", n),
+            Trigger::Below(n) => write!(f, "population below {}", n),
+            Trigger::Cell(x, y) => write!(f, "cell {},{} came alive", x, y),
+        }
+    }
+}
+
+
 //// UI FNs ////
 
 // creates the string for the toolbar.
-fn gen_toolbar<I, F>(fg_char: char, bg_char: char, live: I, birth: I, framerate: F) -> String where
+fn gen_toolbar<I, F>(fg_char: char, bg_char: char, live: I, birth: I, framerate: F, pos: (usize, usize)) -> String where
     I: std::fmt::Display,
     F: std::fmt::Display,
 {
-    format!("FG:'{}' BG:'{}' Live:{} Birth:{} FPS:{:.1}", fg_char, bg_char, live, birth, framerate)
+    format!("FG:'{}' BG:'{}' Live:{} Birth:{} FPS:{:.1} XY:{},{}",
+            fg_char, bg_char, live, birth, framerate, pos.0, pos.1)
 }
 
 
-// returns the grid as a long string. ncurses should wrap, so newlines aren't added
-fn grid_to_str(grid: &Vec<Vec<bool>>, char_true: char, char_false: char) -> String {
-    let mut result = String::new();
-    for row in grid{
-        for col in row {
-            match col {
-                true => result.push(char_true),
-                false => result.push(char_false),
+// text drawn each frame, kept between frames so drawing stops allocating once they've grown
+#[derive(Default)]
+struct FrameBuffers {
+    text: String,
+    // the other layer's pane in dual view
+    right: String,
+    // the text so far while it's redone with the other pane or a border
+    spare: String,
+    // one a thread, for big grids
+    bands: Vec<String>,
+    // as last drawn, so it's only drawn again when it changes. Emptied when the screen is.
+    toolbar: String,
+}
+
+
+// writes the grid over result as one long string, one line per row.
+// overlay(x, y, alive) can swap any cell for a styled char, for guides, trails etc.
+// Big grids are split into bands of rows, built across the pool and joined.
+fn grid_to_str<F>(result: &mut String, bands: &mut Vec<String>, grid: &[Vec<bool>],
+                  char_true: char, char_false: char, overlay: F) where
+    F: Fn(usize, usize, bool) -> Option<StyledContent<char>> + Sync,
+{
+    let rows_to_str = |result: &mut String, rows: &[Vec<bool>], first: usize| {
+        result.clear();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, col) in row.iter().enumerate() {
+                match overlay(x, first + y, *col) {
+                    Some(styled) => {write!(result, "{}", styled).unwrap();},
+                    None => result.push(if *col {char_true} else {char_false}),
+                }
             }
+            result.push('\n')
         }
-        result.push('\n')
+    };
+
+    let cells = grid.len() * grid.first().map_or(0, |row| row.len());
+    let threads = pool::threads();
+    if cells < PARALLEL_CELLS || threads == 1 {
+        return rows_to_str(result, grid, 0)
+    }
+    let band = grid.len().div_ceil(threads);
+    bands.resize(grid.len().div_ceil(band), String::new());
+    let texts: Vec<Mutex<&mut String>> = bands.iter_mut().map(Mutex::new).collect();
+    pool::run(texts.len(), |i| {
+        let rows = &grid[i * band..grid.len().min((i + 1) * band)];
+        rows_to_str(&mut texts[i].lock().unwrap(), rows, i * band)
+    });
+    result.clear();
+    for text in bands.iter() {
+        result.push_str(text);
     }
-    result
+}
+
+
+// the part of a line that fits in width columns, not counting escape codes.
+fn truncate_visible(line: &str, width: usize) -> &str {
+    let mut visible = 0;
+    let mut escape = false;
+    for (i, c) in line.char_indices() {
+        if escape {
+            // CSI sequences end on a letter
+            escape = !c.is_ascii_alphabetic();
+        } else if c == '\x1b' {
+            escape = true;
+        } else {
+            if visible == width {return &line[..i]}
+            visible += 1;
+        }
+    }
+    line
+}
+
+
+// writes a grid_to_str() result over result in a frame of the given inner width
+fn frame_str(result: &mut String, text: &str, width: usize) {
+    let edge = |result: &mut String| {
+        result.push('+');
+        result.extend(std::iter::repeat_n('-', width));
+        result.push_str("+\n");
+    };
+    result.clear();
+    edge(result);
+    for line in text.lines() {
+        result.push('|');
+        result.push_str(line);
+        result.push_str("|\n");
+    }
+    edge(result);
+}
+
+
+// a whole screen, cols by rows, as redraw() and queue_toolbar() would leave it but
+// without touching the terminal. The grid's drawn plain, bordered or not, and cut to
+// fit above the toolbar, which is always the last line. Same grid, same string.
+fn render_frame(grid: &[Vec<bool>], char_true: char, char_false: char, border: bool,
+                toolbar: &str, cols: usize, rows: usize) -> String {
+    let (mut text, mut spare, mut bands) = (String::new(), String::new(), Vec::new());
+    grid_to_str(&mut text, &mut bands, grid, char_true, char_false, |_, _, _| None);
+    if border {
+        std::mem::swap(&mut text, &mut spare);
+        frame_str(&mut text, &spare, grid.first().map_or(0, |row| row.len()));
+    }
+    let mut lines = text.lines();
+    let mut screen = String::new();
+    // blank lines under a grid smaller than the screen
+    for _ in 0..rows.saturating_sub(1) {
+        screen.push_str(truncate_visible(lines.next().unwrap_or(""), cols));
+        screen.push('\n');
+    }
+    if rows > 0 {
+        screen.push_str(truncate_visible(toolbar, cols));
+        screen.push('\n');
+    }
+    screen
 }
 
 
 // clears terminal and redraws text.
-fn redraw<T: Write, U: Into<usize>,>(buff: &mut T, text: &str, col_max: U, row_max: U) {
+fn redraw<T: Write, U: Into<usize>,>(buff: &mut T, text: &str, col_max: U, row_max: U) -> Result<()> {
     let col_max: usize = col_max.into();
     let row_max: usize = row_max.into();
-    let mut i = 0;
-    buff.queue(cursor::SavePosition).unwrap();
+    buff.queue(cursor::SavePosition)?;
 
-    for slice in text.split('\n') {
+    for (i, slice) in text.split('\n').enumerate() {
         if i > row_max - 1 {break};
 
-        let mut slice = String::from(slice);
-        if slice.len() > col_max {
-            slice.truncate(col_max);
+        let slice = truncate_visible(slice, col_max);
+
+        buff.queue(cursor::MoveTo(0, i as u16))?
+            .queue(Print(slice))?;
+    }
+
+    buff.queue(cursor::RestorePosition)?
+        .flush()?;
+    Ok(())
+}
+
+
+// queues the toolbar over the bottom row, for the next redraw() to send with the grid
+fn queue_toolbar<T: Write>(buff: &mut T, text: &str, col_max: u16, row_max: u16) -> Result<()> {
+    buff.queue(cursor::SavePosition)?
+        .queue(cursor::MoveTo(0, row_max - 1))?
+        .queue(terminal::Clear(terminal::ClearType::UntilNewLine))?
+        .queue(Print(truncate_visible(text, col_max as usize)))?
+        .queue(cursor::RestorePosition)?;
+    Ok(())
+}
+
+
+// a box of text in a top corner, over whatever's drawn. Leaves the toolbar alone.
+fn draw_panel<T: Write>(buff: &mut T, lines: &[String], right: bool, col_max: u16, row_max: u16) -> Result<()> {
+    let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0).min(col_max.saturating_sub(4) as usize);
+    let left = if right {col_max.saturating_sub(width as u16 + 3)} else {1};
+    buff.queue(cursor::SavePosition)?;
+    for (i, line) in lines.iter().take(row_max.saturating_sub(3) as usize).enumerate() {
+        let line: String = line.chars().take(width).collect();
+        buff.queue(cursor::MoveTo(left, i as u16 + 1))?
+            .queue(Print(style(format!(" {:width$} ", line, width = width)).reverse()))?;
+    }
+    buff.queue(cursor::RestorePosition)?
+        .flush()?;
+    Ok(())
+}
+
+
+// puts text on the clipboard with OSC 52, which most terminals pass on
+// even over ssh. Ones that don't just ignore it.
+fn copy_to_clipboard<T: Write>(buff: &mut T, text: &str) -> Result<()> {
+    const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in text.as_bytes().chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - i * 8));
+        for i in 0..4 {
+            encoded.push(if i <= chunk.len() {BASE64[(n >> (18 - i * 6)) as usize & 63] as char} else {'='});
         }
+    }
+    buff.queue(Print(format!("\x1b]52;c;{}\x07", encoded)))?.flush()?;
+    Ok(())
+}
+
+
+// one of --snapshot-every's files, named by generation so they sort in order.
+// crop is a box and margin for pictures, the same every time so they line up as a time-lapse.
+fn write_snapshot(dir: &Path, png: bool, crop: Option<Crop>, grid: &[Vec<bool>],
+                  generation: u64, rule: &str) -> std::result::Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let path = dir.join(format!("gen_{:08}.{}", generation, if png {"png"} else {"rle"}));
+    if png {
+        let view = crop.map_or_else(|| grid.to_vec(), |(bbox, margin)| crop_margin(grid, bbox, margin));
+        std::fs::write(&path, image::png(&view)).map_err(|e| format!("{}: {}", path.display(), e))
+    } else {
+        let mut pattern = Pattern::new(grid.to_vec(), Some(rule.to_string()));
+        pattern.generation = Some(generation);
+        pattern::save(&path, &pattern)
+    }
+}
+
 
-        buff.queue(cursor::MoveTo(0, i as u16))
-            .unwrap()
-            .queue(Print(slice))
-            .unwrap();
+// a GIF frame's delay at a framerate. Most viewers slow anything under 2 hundredths down.
+fn gif_delay(framerate: f64) -> u16 {
+    (100. / framerate).round().clamp(2., u16::MAX as f64) as u16
+}
 
-        i += 1;
+
+// for the timing overlay, eg "412us" or "3.1ms"
+fn short_duration(duration: Duration) -> String {
+    match duration.as_micros() {
+        us if us < 1000 => format!("{}us", us),
+        us => format!("{:.1}ms", us as f64 / 1000.),
     }
+}
 
-    buff.queue(cursor::RestorePosition)
-        .unwrap()
-        .flush()
-        .unwrap();
+
+fn short_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b < 1 << 20 => format!("{:.1}KB", b as f64 / 1024.),
+        b => format!("{:.1}MB", b as f64 / (1 << 20) as f64),
+    }
+}
+
+
+// what a grid of any cell type takes up, rows and all
+fn grid_bytes<T>(grid: &[Vec<T>]) -> usize {
+    std::mem::size_of_val(grid) + grid.iter().map(|row| std::mem::size_of_val(row.as_slice())).sum::<usize>()
+}
+
+
+// how much of our memory is actually in RAM. Only Linux says, through /proc.
+fn resident_memory() -> Option<usize> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: usize = statm.split_whitespace().nth(1)?.parse().ok()?;
+    #[cfg(unix)]
+    let page_size = unsafe {libc::sysconf(libc::_SC_PAGESIZE)}.max(0) as usize;
+    #[cfg(not(unix))]
+    let page_size = 4096;
+    Some(pages * page_size)
 }
 
 
@@ -148,18 +907,224 @@ fn valid_chars(c: char) -> bool{
 }
 
 
+// index of the framerate closest to fps
+fn nearest_framerate(framerates: &[f64], fps: f64) -> usize {
+    (0..framerates.len()).min_by(|a, b| {
+        (framerates[*a] - fps).abs().partial_cmp(&(framerates[*b] - fps).abs()).unwrap()
+    }).unwrap_or(0)
+}
+
+
 // get crossterm event with optional poll duration.
-fn get_event(duration: Option<Duration>) -> Option<Event>{
-    match duration {
-        Some(delay) => {
-            if poll(delay).unwrap() {
-                Some(read().unwrap())
-            } else {None}
-        },
-        None => Some(read().unwrap())
+// Errors with Error::Terminated once a signal asks us to close or the terminal's gone.
+fn get_event(duration: Option<Duration>) -> Result<Option<Event>>{
+    // waits are taken in turns, to notice signals and take turns with a gamepad
+    let turn = if gamepad::connected() {GAMEPAD_POLL} else {SIGNAL_POLL};
+    let start = Instant::now();
+    loop {
+        if terminated() {return Err(Error::Terminated)}
+        if let Some(event) = gamepad::next() {return Ok(Some(event))}
+        // crossterm 0.18 only passes on key presses, Windows consoles included, so there are
+        // no releases to filter out. Past 0.26 they'd need KeyEventKind::Press checked here.
+        if poll(Duration::ZERO)? {return Ok(Some(read()?))}
+        let left = duration.map(|d| d.saturating_sub(start.elapsed()));
+        if left == Some(Duration::ZERO) {return Ok(None)}
+        if !wait_for_terminal(left.map_or(turn, |left| left.min(turn)))? {return Err(Error::Terminated)}
+    }
+}
+
+
+// what the undo tree calls the change an event made, and whether it can join more of
+// the same. Steps and strokes come off in one go, anything else an event at a time.
+fn edit_label(event: &Event) -> (String, bool) {
+    match event {
+        Event::Key(KeyEvent{code: KeyCode::Char(c), modifiers}) if modifiers.contains(KeyModifiers::CONTROL) =>
+            (format!("ctrl-{}", c), false),
+        Event::Key(KeyEvent{code: KeyCode::Char('e' | 'f' | 'J'), ..}) => ("run".to_string(), true),
+        Event::Key(KeyEvent{code: KeyCode::Char('r'), ..}) => ("rewind".to_string(), true),
+        Event::Key(KeyEvent{code: KeyCode::Char('k'), ..}) => ("scrub".to_string(), false),
+        // with a brush on
+        Event::Key(KeyEvent{code: KeyCode::Char('w' | 'a' | 's' | 'd'), ..}) => ("paint".to_string(), true),
+        Event::Key(KeyEvent{code: KeyCode::Char(' '), ..}) => ("toggle".to_string(), false),
+        Event::Key(KeyEvent{code: KeyCode::Char(c), ..}) => (c.to_string(), false),
+        Event::Key(KeyEvent{code: KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right, ..}) => ("move".to_string(), true),
+        Event::Mouse(_) => ("draw".to_string(), false),
+        _ => ("edit".to_string(), false),
+    }
+}
+
+
+// waits up to so long for terminal input. crossterm spins forever reading a terminal
+// that's hung up, so on unix this polls it first. false if it has hung up.
+#[cfg(unix)]
+fn wait_for_terminal(wait: Duration) -> Result<bool> {
+    // crossterm reads stdin when it's the terminal, otherwise /dev/tty
+    if unsafe {libc::isatty(libc::STDIN_FILENO)} == 0 {
+        poll(wait)?;
+        return Ok(true)
+    }
+    let mut fd = libc::pollfd {fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0};
+    unsafe {libc::poll(&mut fd, 1, wait.as_millis() as libc::c_int)};
+    Ok(fd.revents & (libc::POLLHUP | libc::POLLERR | libc::POLLNVAL) == 0)
+}
+
+#[cfg(not(unix))]
+fn wait_for_terminal(wait: Duration) -> Result<bool> {
+    poll(wait)?;
+    Ok(true)
+}
+
+
+// the grid needs at least one row above the toolbar
+fn big_enough(cols: u16, rows: u16) -> bool {
+    cols >= 1 && rows >= 2
+}
+
+
+// if the terminal is too small to play in, show a notice until it's resized.
+// 'q' gives up and ends the session instead.
+fn wait_for_size<T: Write>(buff: &mut T, cols: u16, rows: u16) -> Result<(u16, u16)> {
+    let (mut cols, mut rows) = (cols, rows);
+    while !big_enough(cols, rows) {
+        buff.queue(terminal::Clear(terminal::ClearType::All))?;
+        redraw(buff, "terminal too small", cols, rows)?;
+        match get_event(None)? {
+            Some(Event::Resize(ncols, nrows)) => {
+                cols = ncols;
+                rows = nrows;
+            },
+            Some(Event::Key(KeyEvent{code: KeyCode::Char('q'), modifiers: _})) => return Err(Error::TooSmall),
+            _ => (),
+        }
+    }
+    Ok((cols, rows))
+}
+
+
+// drawing over the normal screen, rather than the alternate one. Set once before the UI starts.
+static INLINE: AtomicBool = AtomicBool::new(false);
+
+// terminals that likely can't switch screens. Windows consoles can but don't set TERM.
+fn no_alternate_screen() -> bool {
+    let dumb = match std::env::var("TERM") {
+        Ok(term) => term.is_empty() || term == "dumb",
+        Err(_) => !cfg!(windows),
+    };
+    dumb || std::env::var_os("CI").is_some()
+}
+
+// the alternate screen, or a clean one to draw inline on
+fn enter_screen<T: Write>(buff: &mut T) -> Result<()> {
+    if INLINE.load(Ordering::Relaxed) {
+        buff.queue(terminal::Clear(terminal::ClearType::All))?;
+    } else {
+        buff.queue(terminal::EnterAlternateScreen)?;
+    }
+    Ok(())
+}
+
+
+// leaves the alternate screen and raw mode. Shared by normal quitting and the panic hook,
+// so errors are ignored since there's nothing left to do about them anyway.
+// Inline, the last frame's left up and the shell carries on under it.
+fn restore_terminal() {
+    let mut stdo = stdout();
+    let _ = stdo.queue(Print(SYNC_END));
+    if INLINE.load(Ordering::Relaxed) {
+        let rows = terminal::size().map_or(1, |(_, rows)| rows);
+        let _ = queue!(stdo, cursor::MoveTo(0, rows.saturating_sub(1)), Print("\n"));
+    } else {
+        let _ = stdo.queue(terminal::LeaveAlternateScreen);
+    }
+    let _ = queue!(
+        stdo,
+        event::DisableMouseCapture,
+        cursor::Show,
+        cursor::EnableBlinking,
+        );
+    let _ = stdo.flush();
+    let _ = terminal::disable_raw_mode();
+}
+
+
+// stdout held a frame at a time. Unlike a BufWriter, what's held when a panic unwinds
+// is thrown away, or it'd be written over the shell after the hook's restored the terminal.
+struct Screen {
+    held: Vec<u8>,
+    out: std::io::StdoutLock<'static>,
+}
+
+impl Screen {
+    fn new() -> Self {
+        Screen {held: Vec::with_capacity(OUT_BUFFER), out: stdout().lock()}
+    }
+}
+
+impl Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.held.len() + buf.len() > OUT_BUFFER {self.flush()?}
+        self.held.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.out.write_all(&self.held)?;
+        self.held.clear();
+        self.out.flush()
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {let _ = self.flush();}
+    }
+}
+
+
+// Ctrl-Z. Raw mode stops the tty from sending SIGTSTP itself, so hand the terminal back
+// and stop ourselves. Returns once resumed with `fg`, leaving the caller to redraw.
+#[cfg(unix)]
+fn suspend() -> Result<()> {
+    restore_terminal();
+    unsafe {libc::raise(libc::SIGTSTP);}
+    terminal::enable_raw_mode()?;
+    enter_screen(&mut stdout())?;
+    queue!(
+        stdout(),
+        cursor::DisableBlinking,
+        event::EnableMouseCapture,
+        )?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn suspend() -> Result<()> {
+    Ok(())
+}
+
+
+// set by SIGTERM and SIGHUP, like when the terminal window closes.
+// get_event() notices, so the terminal's restored on the way out as usual.
+static TERMINATED: AtomicBool = AtomicBool::new(false);
+
+fn terminated() -> bool {
+    TERMINATED.load(Ordering::Relaxed)
+}
+
+#[cfg(unix)]
+fn catch_signals() {
+    extern "C" fn on_signal(_: libc::c_int) {
+        TERMINATED.store(true, Ordering::Relaxed);
+    }
+    unsafe {
+        libc::signal(libc::SIGTERM, on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        libc::signal(libc::SIGHUP, on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t);
     }
 }
 
+#[cfg(not(unix))]
+fn catch_signals() {}
+
 
 //// Standalone macros ////
 
@@ -168,27 +1133,173 @@ macro_rules! KE {
     ($ch:expr) => {
         Event::Key(KeyEvent{code: KeyCode::Char($ch), modifiers: _})
     };
-    ($ch:expr, $mod:expr) => {
+    ($ch:expr, $mod:pat) => {
         Event::Key(KeyEvent{code: KeyCode::Char($ch), modifiers: $mod})
     };
 }
 
 
+// how many cell values the predecessor search tries before giving up
+const SEARCH_LIMIT: u64 = 20_000_000;
+
+// lowest cosmic ray chance above none that '{' and '}' step through
+const COSMIC_MIN: f64 = 1e-6;
+
+// how many cells across the blobs from 'G' noise are
+const NOISE_SCALE: f64 = 6.;
+
+// how often a jump to a generation shows how far it's got
+const JUMP_PROGRESS: Duration = Duration::from_millis(100);
+// how often --watch checks the pattern file
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+// how long get_event waits on the terminal before checking for signals again
+const SIGNAL_POLL: Duration = Duration::from_millis(100);
+// and a gamepad, which wants to be checked more often
+const GAMEPAD_POLL: Duration = Duration::from_millis(20);
+
+// grids with fewer cells than this draw faster on one thread than split between them
+const PARALLEL_CELLS: usize = 1 << 14;
+// and step faster on one. Stepping's more work a cell, so it's worth splitting sooner.
+const STEP_PARALLEL_CELLS: usize = 1 << 10;
+// Stepper goes back to stepping every cell once more than one in this many flipped
+const SPARSE_SHARE: usize = 8;
+// synchronized output. Terminals that know it hold the screen until the end, so a frame's
+// never seen half drawn. crossterm doesn't have it yet, and the rest ignore it.
+const SYNC_BEGIN: &str = "\x1b[?2026h";
+const SYNC_END: &str = "\x1b[?2026l";
+// bytes of output held until a flush, enough for a frame on a big terminal
+const OUT_BUFFER: usize = 1 << 18;
+
+// where 'S' saves when no pattern was loaded
+const DEFAULT_SAVE: &str = "cursed_life.rle";
+// the comment 'sign_saves' adds
+const SIGNATURE: &str = "edited with cursed_life";
+// the most room a pattern's picture gets in the 'O' browser
+const THUMB_COLS: usize = 24;
+const THUMB_LINES: usize = 4;
+
+// where --snapshot-every puts them
+const DEFAULT_SNAPSHOT_DIR: &str = "cursed_life_snapshots";
+// where 'I' saves its picture when it can't copy it
+const SCREENSHOT_SAVE: &str = "cursed_life.png";
+
+// drawn over dead cells in the cursor's row and column when guides are on
+const GUIDE_CHAR: char = '.';
+
+// shades for recently died cells, newest first. Also sets how long trails last.
+const TRAIL_COLORS: [Color; 4] = [
+    Color::AnsiValue(246),
+    Color::AnsiValue(242),
+    Color::AnsiValue(238),
+    Color::AnsiValue(235),
+];
+
+// preview colors for cells about to be born and die
+const BORN_COLOR: Color = Color::Green;
+const DIE_COLOR: Color = Color::Red;
+
+// background flashed behind cells that changed in the last step
+const DIFF_COLOR: Color = Color::DarkYellow;
+// how long the flash lasts during playback. Only used when frames are at least twice as long.
+const DIFF_FLASH: Duration = Duration::from_millis(100);
+
+// shades for the previous generations under the onion skin, newest first. Also sets the depth.
+const ONION_COLORS: [Color; 2] = [
+    Color::AnsiValue(67),
+    Color::AnsiValue(60),
+];
+// red and blue for two-species mode
+const SPECIES_COLORS: [Color; 2] = [Color::Red, Color::Blue];
+// a pattern waiting to be placed
+const GHOST_COLOR: Color = Color::AnsiValue(244);
+// behind the selected rectangle
+const SELECTION_COLOR: Color = Color::AnsiValue(24);
+// behind the cell distances are measured from
+const ANCHOR_COLOR: Color = Color::DarkGreen;
+// text of labels pinned with 'N'
+const LABEL_COLOR: Color = Color::Yellow;
+// behind cells marked with 'M'
+const MARKED_COLOR: Color = Color::DarkMagenta;
+// behind dead cells that have been alive, when showing LifeHistory
+const HISTORY_COLOR: Color = Color::AnsiValue(237);
+// behind the cell under the cursor while it's hidden for playback
+const PLAY_CURSOR_COLOR: Color = Color::DarkYellow;
+// live cells of the layer not being edited
+const LAYER_COLOR: Color = Color::DarkCyan;
+// cells alive now and not in the bookmark 'Y' compares with, and the other way round
+const ADDED_COLOR: Color = Color::Green;
+const REMOVED_COLOR: Color = Color::Red;
+
+
 const HELP_TEXT: &str =
 "Controls:
 wasd  : move
 space : toggle gridpoint
 e     : frame advance
+r     : rewind a generation
+J     : jump to a generation, stepping there without drawing, or back if it's kept for r
+k     : scrub through the generations r could rewind to. ad or arrows a generation,
+        ws or page up/down a tenth of the way, enter keeps one, esc goes back
+u     : undo the last change to the grid, edits and steps alike. ctrl-r redoes it
+ctrl-u: show the undo tree, where undoing and changing something else starts a branch.
+        ws moves the grid about it, enter stays there and esc goes back
 f     : playback
-xx    : clear
-qq    : quit
+xx    : clear. 'confirm' in the config can make it and others ask y/n, or not confirm
+qq    : quit. asks first if there's anything unsaved, marked [+] in the toolbar
 h     : show/hide this help
+b     : show/hide border
+i     : invert the display
+S     : save (S whole grid, c crop to live cells)
+o     : open a pattern and place it at the cursor
+ctrl-o: browse for a pattern or session file to open
+ctrl-s: save to a new file, then S saves there too
+Q     : open one of the last few files opened or saved
+j     : show the name, author and comments of the last pattern loaded
+ctrl-b: bookmark the grid as it is
+Y     : show/hide cells added (green) and removed (red) since the bookmark, or
+        without one since a grid saved whole or a snapshot file
+y     : show/hide step, draw and event times and memory use in the corner
+O     : pick a built in pattern to place from pictures of them
+W     : paste or type a pattern, plaintext or RLE, to stamp at the cursor
+T     : tile a pattern across the grid
+R     : search for a predecessor of the grid
+I     : copy a picture of the grid to the clipboard, with wl-copy or xclip
+C     : copy a seed code of the grid, or just the selection, and rule to the clipboard
+G     : replace the grid from a generator. u uniform soup, n noise blobs,
+        s stripes, k checkerboard, d diagonals, o circle, r ring
+g     : show/hide cursor guides
+P     : show/hide the cursor's cell during playback
+t     : show/hide trails
+H     : show/hide every cell that's ever been alive
+M     : mark/unmark a cell. Kept when saving, as LifeHistory
+v     : select a rectangle. v at each corner, again to drop it.
+        the toolbar shows its size, population and live cells' bounds
+X     : mirror the grid, h left to right or v top to bottom, or r rotate it clockwise.
+        rotating a grid that isn't square loses the corners
+Z     : double the size of the selection, or everything on the grid, as 2x2 blocks
+B     : cycle paint and erase modes, where moving sets cells alive or dead
+F     : flood fill the dead cells joined to the cursor's, or clear the live ones
+arrows: move the selection's cells, putting back what they covered
+U     : lasso. U starts tracing as the cursor moves, again closes it, again drops it
+K     : draw a shape in the selection. r rectangle, e ellipse, R and E filled
+A     : measure from the cursor. the toolbar shows dx,dy and the distance
+N     : pin a text label to a cell, or remove it with an empty one
+n     : show/hide onion skin of past generations
+p     : show/hide births and deaths of the next step
+D     : flash cells changed by each step
+E     : cycle what's past the edges of the grid
+l     : switch layer, adding a second the first time
+L     : remove the layer not being edited
+V     : dual view. copy the grid into a second pane with its own rules
+m     : two-species mode. space cycles a cell through red and blue
+ctrl-z: suspend
 lmb   : draw
 rmb   : erase
 
 Game of Life rules:
 minus/equals '-=' : adjust 'lives' rule
 brackets '[]'     : adjust 'birth' rule
+braces '{}'       : less/more cosmic rays flipping random cells
 
 System settings:
 comma/period ',.' : adjust max framerate
@@ -196,48 +1307,458 @@ c                 : change characters
 
 Command flags:
 -l : log performance stats
--h : print this help and exit";
+-h : print this help and exit
+--watch         : reload the pattern file whenever it changes
+--skip-help     : start at the grid instead of these controls. also 'help_at_start = no' in the config
+--inline        : draw over the terminal's own screen, for ones without an alternate screen.
+                  On by default when TERM is dumb or unset, or under CI
+--edges MODE    : what's past the edges. dead, torus, klein, cross or shifted.
+                  A checkpoint's own by default
+--inject N      : drop a glider in from the edge every N generations
+--inject-dir D  : heading of injected gliders, se sw ne or nw
+--inject-pattern FILE : inject this pattern instead, from the top for s* and bottom for n*
+--seed N        : seed for anything random
+--code CODE     : start from a seed code made by 'C' or the code subcommand
+--demo NAME     : play a built in pattern. glider, lwss, r-pentomino, acorn, diehard,
+                  pulsar, pentadecathlon or gosper-gun
+--checkpoint-every N : save the grid every N generations of playback
+--checkpoint FILE    : where checkpoints go, cursed_life.checkpoint.rle by default
+--snapshot-every N   : also save numbered snapshots every N generations of playback
+--snapshot-dir DIR   : where snapshots go, cursed_life_snapshots by default
+--snapshot-format F  : rle or png
+--gif FILE           : record playback to an animated GIF, written as it plays
+--gif-every N        : only record every Nth generation, for a time-lapse
+--autosave FILE      : save the grid here if the terminal closes or we're killed
+--log-file FILE      : write events, errors, resizes and step/draw times here
+--step-threads N     : step on N threads. 1 keeps to one, by default that's grids under 1024 cells
+--gamepad DEVICE     : also take input from a joystick like /dev/input/js0.
+                       d-pad moves, A toggles, start plays
+--cosmic P      : flip each cell with chance P every generation
+--stop-above N  : stop playback once the population is over N
+--stop-below N  : stop playback once the population is under N
+--stop-cell X,Y : stop playback when cell X,Y comes alive
+a file path loads an RLE or plaintext pattern in the middle of the grid
+
+Subcommands:
+info FILE...
+    print the size, population, rule and comments of patterns
+run FILE [--gens N] [--rule B3/S23] [--size COLSxROWS] [--edges MODE]
+         [--checkpoint-every N] [--checkpoint FILE] [-o FILE] [--json] [--step-threads N]
+    step a pattern without the UI and report how it ends.
+    --resume CHECKPOINT in place of FILE carries on from a checkpoint, up to --gens in total.
+    exits 0 if it settled, 2 if it died out, 3 if it hit the cap and 1 on errors
+render FILE [--size COLSxROWS] [--gens N] [--rule B3/S23] [--edges MODE] [--border] [--alive C] [--dead C]
+    print the screen a terminal that size would show after N generations, toolbar and all.
+    ignores the config, so it's the same every time
+code FILE
+    print a seed code for a pattern, to start from with --code
+convert IN OUT
+    convert a pattern between formats, picked by extension
+soup [--count N] [--size N] [--rule B3/S23] [--gens N] [--seed N] [--noise SCALE] [-o FILE]
+    run random soups on all cores and report lifetimes and the objects left.
+    --noise makes blobby soups from value noise SCALE cells across
+bench [--seeds N] [--size COLSxROWS] [--gens N] [--rule B3/S23] [--edges MODE] [--density P] [--seed N]
+    step the same random soups with every engine, plain, threaded and sparse, and time them.
+    fails if any steps somewhere the plain one didn't
+
+Chars, colors, framerates and extra key bindings can be set in
+~/.config/cursed_life/config, which is reloaded whenever it changes.";
+
+
+// everything from the command line
+struct Options {
+    help: bool,
+    log: bool,
+    watch: bool,
+    path: Option<PathBuf>,
+    triggers: Vec<Trigger>,
+    // None to go with a checkpoint's, or dead
+    edges: Option<Edges>,
+    inject: Option<Inject>,
+    seed: u64,
+    cosmic: f64,
+    // a seed code to start from, in place of a file
+    code: Option<String>,
+    // a built in pattern to start playing straight away
+    demo: Option<String>,
+    // start at the grid rather than the controls
+    skip_help: bool,
+    // draw over the normal screen instead of the alternate one
+    inline: bool,
+    // where the grid goes if we're closed by a signal
+    autosave: Option<PathBuf>,
+    checkpoint_every: Option<u64>,
+    checkpoint: PathBuf,
+    // a joystick device to take input from too
+    gamepad: Option<PathBuf>,
+    // numbered pictures or patterns of the grid, for a record of a long run
+    snapshot_every: Option<u64>,
+    snapshot_dir: PathBuf,
+    snapshot_png: bool,
+    // record playback to an animated GIF, every so many generations
+    gif: Option<PathBuf>,
+    gif_every: u64,
+    // what happened, for looking into problems after
+    log_file: Option<PathBuf>,
+    // threads to step on, None to pick by grid size
+    step_threads: Option<usize>,
+}
+
+
+fn parse_args() -> Result<Options> {
+    let mut options = Options {help: false, log: false, watch: false, path: None, triggers: Vec::new(), edges: None,
+        inject: None, seed: rng::time_seed(), cosmic: 0., code: None, demo: None, skip_help: false, inline: false, autosave: None,
+        checkpoint_every: None, checkpoint: PathBuf::from(headless::DEFAULT_CHECKPOINT), gamepad: None,
+        snapshot_every: None, snapshot_dir: PathBuf::from(DEFAULT_SNAPSHOT_DIR), snapshot_png: false,
+        gif: None, gif_every: 1, log_file: None, step_threads: None,
+    };
+    // --inject parts, put together once everything's read
+    let (mut inject_every, mut inject_heading, mut inject_pattern) = (None, "se".to_string(), None);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" => options.help = true,
+            "-l" => options.log = true,
+            "--watch" => options.watch = true,
+            "--skip-help" => options.skip_help = true,
+            "--inline" => options.inline = true,
+            "--code" => options.code = Some(args.next().ok_or_else(|| Error::Usage(format!("{} needs a value", arg)))?),
+            "--demo" => options.demo = Some(args.next().ok_or_else(|| Error::Usage(format!("{} needs a value", arg)))?),
+            "--edges" => {
+                let value = args.next().ok_or_else(|| Error::Usage(format!("{} needs a value", arg)))?;
+                options.edges = Some(Edges::parse(&value)
+                    .ok_or_else(|| Error::Usage(format!("bad value '{}' for {}", value, arg)))?);
+            },
+            "--inject" | "--inject-dir" | "--inject-pattern" | "--seed" | "--cosmic"
+            | "--checkpoint-every" | "--checkpoint" | "--gamepad"
+            | "--snapshot-every" | "--snapshot-dir" | "--snapshot-format" | "--autosave"
+            | "--gif" | "--gif-every" | "--log-file" | "--step-threads" => {
+                let value = args.next().ok_or_else(|| Error::Usage(format!("{} needs a value", arg)))?;
+                let bad = || Error::Usage(format!("bad value '{}' for {}", value, arg));
+                match arg.as_str() {
+                    "--inject" => inject_every = Some(value.parse().ok().filter(|n| *n > 0).ok_or_else(bad)?),
+                    "--inject-dir" => inject_heading = value,
+                    "--inject-pattern" => inject_pattern = Some(pattern::load(Path::new(&value)).map_err(Error::Pattern)?),
+                    "--checkpoint-every" => options.checkpoint_every = Some(value.parse().ok().filter(|n| *n > 0).ok_or_else(bad)?),
+                    "--checkpoint" => options.checkpoint = PathBuf::from(value),
+                    "--gamepad" => options.gamepad = Some(PathBuf::from(value)),
+                    "--autosave" => options.autosave = Some(PathBuf::from(value)),
+                    "--gif" => options.gif = Some(PathBuf::from(value)),
+                    "--gif-every" => options.gif_every = value.parse().ok().filter(|n| *n > 0).ok_or_else(bad)?,
+                    "--log-file" => options.log_file = Some(PathBuf::from(value)),
+                    "--step-threads" => options.step_threads = Some(value.parse().ok().filter(|n| *n > 0).ok_or_else(bad)?),
+                    "--snapshot-every" => options.snapshot_every = Some(value.parse().ok().filter(|n| *n > 0).ok_or_else(bad)?),
+                    "--snapshot-dir" => options.snapshot_dir = PathBuf::from(value),
+                    "--snapshot-format" => options.snapshot_png = match value.as_str() {
+                        "png" => true,
+                        "rle" => false,
+                        _ => return Err(bad()),
+                    },
+                    "--cosmic" => options.cosmic = value.parse().ok().filter(|p| (0. ..=1.).contains(p)).ok_or_else(bad)?,
+                    _ => options.seed = value.parse().map_err(|_| bad())?,
+                }
+            },
+            "--stop-above" | "--stop-below" | "--stop-cell" => {
+                let value = args.next().ok_or_else(|| Error::Usage(format!("{} needs a value", arg)))?;
+                let trigger = Trigger::parse(&arg, &value)
+                    .ok_or_else(|| Error::Usage(format!("bad value '{}' for {}", value, arg)))?;
+                options.triggers.push(trigger);
+            },
+            _ if arg.starts_with('-') => return Err(Error::Usage(format!("unknown flag {}", arg))),
+            _ => options.path = Some(PathBuf::from(arg)),
+        }
+    }
+    if let Some(every) = inject_every {
+        let mut inject = Inject::glider(every, &inject_heading)
+            .ok_or_else(|| Error::Usage(format!("bad value '{}' for --inject-dir", inject_heading)))?;
+        // a custom pattern goes in as drawn, the heading just picks the edge
+        if let Some(pattern) = inject_pattern.filter(|p| p.width() > 0) {inject.cells = pattern.cells}
+        options.inject = Some(inject);
+    }
+    if options.code.is_some() && options.path.is_some() {
+        return Err(Error::Usage("--code and a pattern file can't both be given".to_string()))
+    }
+    if options.watch && options.path.is_none() {
+        return Err(Error::Usage("--watch needs a pattern file".to_string()))
+    }
+    Ok(options)
+}
+
+
+// for the info subcommand
+fn print_info(path: &str, pattern: &Pattern) {
+    println!("{}", path);
+    println!("size: {}x{}", pattern.width(), pattern.height());
+    println!("population: {}", pattern.population());
+    if let Some(rule) = &pattern.rule {println!("rule: {}", rule)}
+    for line in metadata(pattern) {
+        println!("{}", line);
+    }
+}
+
+
+// the name, author and comments a pattern came with, a line each
+fn metadata(pattern: &Pattern) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(name) = pattern.name() {lines.push(format!("name: {}", name))}
+    if let Some(author) = pattern.author() {lines.push(format!("author: {}", author))}
+    lines.extend(pattern.notes().iter().filter(|n| !n.is_empty()).map(|note| format!("comment: {}", note)));
+    lines
+}
+
+
+// prints an error and exits
+fn fail<E: fmt::Display>(e: E) -> ! {
+    eprintln!("cursed_life: {}", e);
+    std::process::exit(1);
+}
 
 
 fn main() {
-    // -h flag
-    match std::env::args().find(|x| x == "-h") {
-        Some(_) => {
-            println!("{}", HELP_TEXT);
+    // subcommands run without the UI
+    match std::env::args().nth(1).as_deref() {
+        Some("soup") => {
+            let options = soup::parse_args(std::env::args().skip(2)).unwrap_or_else(|e| fail(Error::Usage(e)));
+            soup::run(options).unwrap_or_else(|e| fail(format!("failed to write report: {}", e)));
+            return
+        },
+        Some("bench") => {
+            let options = bench::parse_args(std::env::args().skip(2)).unwrap_or_else(|e| fail(Error::Usage(e)));
+            bench::run(options).unwrap_or_else(|e| fail(e));
             return
         },
-        None => (),
+        Some("run") => {
+            let options = headless::parse_args(std::env::args().skip(2)).unwrap_or_else(|e| fail(Error::Usage(e)));
+            let status = headless::run(options).unwrap_or_else(|e| fail(e));
+            std::process::exit(status.exit_code())
+        },
+        Some("render") => {
+            let options = render::parse_args(std::env::args().skip(2)).unwrap_or_else(|e| fail(Error::Usage(e)));
+            render::run(options).unwrap_or_else(|e| fail(e));
+            return
+        },
+        Some("info") => {
+            let mut paths = std::env::args().skip(2).peekable();
+            if paths.peek().is_none() {fail(Error::Usage("info needs a pattern file".to_string()))}
+            for path in paths {
+                let pattern = pattern::load(Path::new(&path)).unwrap_or_else(|e| fail(Error::Pattern(e)));
+                print_info(&path, &pattern);
+            }
+            return
+        },
+        Some("code") => {
+            let path = std::env::args().nth(2).unwrap_or_else(|| fail(Error::Usage("code needs a pattern file".to_string())));
+            let pattern = pattern::load(Path::new(&path)).unwrap_or_else(|e| fail(Error::Pattern(e)));
+            println!("{}", pattern::to_code(&pattern));
+            return
+        },
+        Some("convert") => {
+            let paths: Vec<PathBuf> = std::env::args().skip(2).map(PathBuf::from).collect();
+            if paths.len() != 2 {fail(Error::Usage("convert needs an input and output file".to_string()))}
+            let pattern = pattern::load(&paths[0]).unwrap_or_else(|e| fail(Error::Pattern(e)));
+            pattern::save(&paths[1], &pattern).unwrap_or_else(|e| fail(format!("failed to save {}", e)));
+            return
+        },
+        _ => (),
+    }
+
+    let options = parse_args().unwrap_or_else(|e| fail(e));
+
+    // -h flag
+    if options.help {
+        println!("{}", HELP_TEXT);
+        return
+    }
+
+    // pattern file or code. Loaded before the UI starts so a bad one just prints an error.
+    let pattern = match (&options.path, &options.code, &options.demo) {
+        (Some(path), _, _) => {
+            let pattern = pattern::load(path).unwrap_or_else(|e| fail(Error::Pattern(e)));
+            let _ = recent::add(path);
+            Some(pattern)
+        },
+        (_, Some(code), _) => Some(pattern::from_code(code).unwrap_or_else(|e| fail(Error::Usage(e)))),
+        (_, _, Some(name)) => Some(library::get(name).unwrap_or_else(|| fail(Error::Usage(
+            format!("no demo '{}', try one of {}", name, library::names().join(", ")))))),
+        _ => None,
+    };
+    if let Some(device) = &options.gamepad {
+        gamepad::open(device).unwrap_or_else(|e| fail(format!("failed to open gamepad {}", e)));
+    }
+    if let Some(path) = &options.log_file {
+        logfile::open(path).unwrap_or_else(|e| fail(format!("failed to open log {}", e)));
+        logfile::write("info", format_args!("cursed_life {} {:?}", env!("CARGO_PKG_VERSION"),
+                                            std::env::args().skip(1).collect::<Vec<_>>()));
     }
 
-    // -l flag
-    let log: bool;
-    match std::env::args().find(|x| x == "-l") {
-        Some(_) => log = true,
-        None => log = false,
+    // restore the terminal before the panic message prints,
+    // otherwise it's lost to the alternate screen and the shell is left in raw mode.
+    // Only from the main thread though, stdout stays locked to it and a drawing
+    // thread would wait forever. Their panics get to the main thread anyway.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().name() == Some("main") {restore_terminal()}
+        logfile::write("error", format_args!("{}", info));
+        default_hook(info);
+    }));
+
+    INLINE.store(options.inline || no_alternate_screen(), Ordering::Relaxed);
+    STEP_THREADS.store(options.step_threads.unwrap_or(0), Ordering::Relaxed);
+    catch_signals();
+    let result = run(options, pattern);
+    if let Err(e) = result {
+        logfile::write("error", format_args!("{}", e));
+        restore_terminal();
+        fail(e);
     }
+}
 
+
+// the interactive session. Errors bubble up to main() to be reported after cleanup.
+fn run(options: Options, pattern: Option<Pattern>) -> Result<()> {
+    let log = options.log;
     // initializations
-    terminal::enable_raw_mode().unwrap();
-    let (mut cols, mut rows) = terminal::size().unwrap();
-    let mut stdo = stdout();
+    terminal::enable_raw_mode()?;
+    // locked for the whole session, with room for a whole frame so each goes out in one write
+    let mut stdo = Screen::new();
+    enter_screen(&mut stdo)?;
+    let (cols, rows) = terminal::size()?;
+    let (mut cols, mut rows) = wait_for_size(&mut stdo, cols, rows)?;
 
     queue!(
         stdo,
-        terminal::EnterAlternateScreen,
         cursor::MoveTo(cols/2, rows/2),
         cursor::DisableBlinking,
         event::EnableMouseCapture,
-        ).unwrap();
+        )?;
+
+    // one-off notice shown in place of the toolbar until the next redraw
+    let mut message: Option<String> = None;
+    let config_path = config::path();
+    let mut config = config_path.as_deref().map_or_else(|| Ok(Config::default()), config::load)
+        .unwrap_or_else(|e| {
+            message = Some(format!("bad config {}", e));
+            Config::default()
+        });
 
     // game data
-    let mut ch_t = 'O';
-    let mut ch_f = ' ';
+    let mut ch_t = config.ch_t;
+    let mut ch_f = config.ch_f;
     let mut live: i32 = 2;
     let mut birth: i32 = 3;
-    let framerates = [0.5, 1., 2., 5., 10., 15., 20., 30., 45., 60., 90., 120., 999.];
-    let mut framerate = 5; // 15.
+    let mut edges = options.edges.unwrap_or(Edges::Dead);
+    let mut rng = Rng::new(options.seed);
+    // chance of each cell flipping every generation
+    let mut cosmic = options.cosmic;
+    let mut framerate = nearest_framerate(&config.framerates, 15.);
+    // 'S' writes back to the pattern that was loaded, if any
+    let mut save_path = options.path.unwrap_or_else(|| PathBuf::from(DEFAULT_SAVE));
+
+    // the border takes a cell off each side of the grid, as long as there's room for it.
+    let mut border = false;
+    // the other layer in a pane of its own to the right, rather than underneath
+    let mut dual = false;
+    let mut off: u16 = 0;
+    let mut guides = false;
+    // mark the cursor's cell during playback
+    let mut play_cursor = false;
+    let mut trails = false;
+    // LifeHistory, shade every cell that's been alive
+    let mut show_history = false;
+    let mut onion_skin = false;
+    let mut preview = false;
+    let mut diff = false;
+    // set by each step, cleared by the next redraw, so changes only flash once
+    let mut flashing = false;
+    let mut playing = false;
+
+    // the cursor is tracked here rather than queried, which also lets
+    // redraw_all!() know where to put the guides.
+    let (mut cur_col, mut cur_row) = (cols/2, rows/2);
+
+    // cols, rows of the grid itself
+    macro_rules! grid_size {
+        () => {{
+            let width = (cols - off*2) as usize;
+            // a column between the panes
+            let size = (if dual {(width.saturating_sub(1) / 2).max(1)} else {width}, (rows - 1 - off*2) as usize);
+            // the rest of the screen is left blank
+            match config.max_size {
+                Some((max_cols, max_rows)) => (size.0.min(max_cols), size.1.min(max_rows)),
+                None => size,
+            }
+        }}
+    }
 
-    let mut matrix = gen_grid(cols as usize, rows as usize - 1, None);
+    let (grid_cols, grid_rows) = grid_size!();
+    let mut matrix = gen_grid(grid_cols, grid_rows, None);
+    // whether anything a save would write has changed since the last save or load
+    let mut dirty = false;
+    // a grid kept with ctrl-b or read from a file, and whether 'Y' is showing what's changed since
+    let mut bookmark: Option<Vec<Vec<bool>>> = None;
+    let mut compare = false;
+    // generations since each cell died, counting down. Only kept while trails are on.
+    let mut trail: Vec<Vec<u8>> = gen_grid(grid_cols, grid_rows, None);
+    // cells that have ever been alive, and cells marked by hand, as in LifeHistory
+    let mut envelope: Vec<Vec<bool>> = gen_grid(grid_cols, grid_rows, None);
+    let mut marked: Vec<Vec<bool>> = gen_grid(grid_cols, grid_rows, None);
+    // text pinned to cells for notes, drawn over the grid from that cell rightwards
+    let mut labels: Vec<(usize, usize, String)> = Vec::new();
+    // paint or erase mode, where the cursor sets cells alive or dead as it moves
+    let mut brush: Option<bool> = None;
+    // a cell to measure the cursor's distance from
+    let mut anchor: Option<(usize, usize)> = None;
+    // a rectangle of the grid, and the corner it's being dragged from while it's made
+    let mut selection: Option<(usize, usize, usize, usize)> = None;
+    let mut selecting: Option<(usize, usize)> = None;
+    // the path so far while a lasso's traced, and which cells it took in after.
+    // A lasso selection is its bounding box, only counting the cells in here.
+    let mut tracing: Option<Vec<(usize, usize)>> = None;
+    let mut lasso: Option<Vec<Vec<bool>>> = None;
+    // the grid without the selection while arrows are moving it. Put down for good by any other key.
+    let mut floating: Option<Vec<Vec<bool>>> = None;
+    // the last few generations, newest first, for the onion skin
+    let mut onion: Vec<Vec<Vec<bool>>> = Vec::new();
+    let mut stepper = Stepper::new();
+    // the other layer, if there are two. shown under the one being edited.
+    let mut layer: Option<Layer> = None;
+    let mut layer_num = 1;
+    // live and dead drawn the other way round
+    let mut inverted = false;
+    // a pattern following the cursor, waiting to be placed
+    let mut ghost: Option<Vec<Vec<bool>>> = None;
+    // the last pattern opened, for reuse
+    let mut last_opened: Option<Vec<Vec<bool>>> = None;
+    // what the last pattern loaded said about itself, and whether it's up on screen
+    let mut about: Vec<String> = Vec::new();
+    let mut show_about = false;
+    let mut frame = FrameBuffers::default();
+    // how long the last step, draw and event handling took, shown in a corner when timing
+    let mut timing = false;
+    let (mut step_time, mut draw_time, mut event_time) = (Duration::ZERO, Duration::ZERO, Duration::ZERO);
+    // the comment lines the pattern being edited came with, written back out on saving
+    let mut comments: Vec<String> = Vec::new();
+    // steps that 'r' can rewind
+    let mut history = History::new(config.history_cap);
+    // edits, steps and everything else that's changed the grids or labels, for 'u' and ctrl-r.
+    // Started again once the grid's set up.
+    let mut undo = UndoTree::new(&[&matrix], &[], 0, config.history_cap);
+    // what the last event was, for naming what it changed
+    let mut undo_label = ("start".to_string(), false);
+    // two-species mode, which species each cell is. true for blue.
+    let mut species: Option<Vec<Vec<bool>>> = None;
+    let mut generation: u64 = 0;
+
+    // for the summary on quitting. generation gets reset and rewound, these don't.
+    let session = Instant::now();
+    let mut simulated: u64 = 0;
+    let mut peak = 0;
+    // --gif, written as it plays, and the box and margin every frame's cropped to
+    let mut recording: Option<(image::Gif<BufWriter<std::fs::File>>, Option<Crop>)> = None;
+    // where it goes, until it's started or failed
+    let mut gif_path = options.gif.clone();
+    // what --snapshot-every pictures crop to, the live cells at the first one, kept for the whole run
+    let mut snapshot_box: Option<(usize, usize, usize, usize)> = None;
 
     let mut draw_times = Vec::<u128>::new();
     let mut step_times = Vec::<u128>::new();
@@ -245,20 +1766,372 @@ fn main() {
 
     //// Macros that use game data ////
 
-    // advance the game one iter
+    // grid coordinates of the ghost's top-left, so it sits centered on the cursor
+    macro_rules! ghost_origin {
+        ($ghost: expr) => {
+            ((cur_col - off) as isize - $ghost[0].len() as isize / 2,
+             (cur_row - off) as isize - $ghost.len() as isize / 2)
+        }
+    }
+
+    // get_event(), saving the grid first if we're being closed and there's somewhere to save it.
+    // A recording's frames are on disk already, it just needs its end.
+    macro_rules! read_event {
+        ($duration: expr) => {
+            match get_event($duration) {
+                Err(Error::Terminated) => {
+                    if let Some(path) = &options.autosave {
+                        let _ = pattern::save(path, &Pattern::new(matrix.clone(), Some(rule_str(live, birth))));
+                    }
+                    if let Some((gif, _)) = recording.take() {let _ = gif.finish();}
+                    return Err(Error::Terminated)
+                },
+                event => {
+                    let event = event?;
+                    if let Some(event) = &event {logfile::write("event", format_args!("{:?}", event))}
+                    event
+                },
+            }
+        }
+    }
+
+    // the next event, with any keys rebound in the config swapped for ours
+    macro_rules! event {
+        ($duration: expr) => {
+            config.remap(read_event!($duration))
+        }
+    }
+
+    // grid coordinates under a screen cell, if it's on the grid at all
+    macro_rules! grid_pos {
+        ($col: expr, $row: expr) => {{
+            let (grid_cols, grid_rows) = grid_size!();
+            if $col >= off && $row >= off &&
+               (($col - off) as usize) < grid_cols && (($row - off) as usize) < grid_rows {
+                Some((($col - off) as usize, ($row - off) as usize))
+            } else {None}
+        }}
+    }
+
+    // (left, top, right, bottom) of the selection, inclusive. follows the cursor while it's being made.
+    // None if the grid's shrunk out from under it.
+    macro_rules! selection {
+        () => {{
+            let (grid_cols, grid_rows) = grid_size!();
+            match selecting {
+                Some((x, y)) => grid_pos!(cur_col, cur_row).map(|(cx, cy)| (x.min(cx), y.min(cy), x.max(cx), y.max(cy))),
+                None => selection,
+            }.filter(|&(_, _, right, bottom)| right < grid_cols && bottom < grid_rows)
+        }}
+    }
+
+    // whether a cell inside the selection's box is part of it, which only a lasso leaves out
+    macro_rules! in_lasso {
+        ($x: expr, $y: expr) => {
+            lasso.as_ref().is_none_or(|mask| mask.get($y).and_then(|row| row.get($x)).copied().unwrap_or(false))
+        }
+    }
+
+    // a copy of what's selected, with anything a lasso left out dead
+    macro_rules! selected_cells {
+        () => {
+            selection!().map(|(left, top, right, bottom)| (top..=bottom).map(|y| (left..=right)
+                .map(|x| matrix[y][x] && in_lasso!(x, y)).collect::<Vec<bool>>()).collect::<Vec<_>>())
+        }
+    }
+
+    // advance the game one iter. true if that changed anything on screen.
     macro_rules! step {
+        () => {{
+            let span = logfile::span("step");
+            peak = peak.max(population(&matrix));
+            let mut next = stepper.step(&matrix, live, birth, edges, generation);
+            // injected and flipped cells are part of the step, so rewinding takes them back out too
+            if let Some(inject) = options.inject.as_ref().filter(|i| (generation + 1).is_multiple_of(i.every)) {
+                inject.drop(&mut next, &mut rng);
+                stepper.forget();
+            }
+            if cosmic > 0. {
+                for (y, row) in next.iter_mut().enumerate() {
+                    for (x, cell) in row.iter_mut().enumerate().filter(|_| rng.chance(cosmic)) {
+                        *cell = !*cell;
+                        stepper.touch(x, y);
+                    }
+                }
+            }
+            // trails keep fading on a still grid
+            let mut changed = next != matrix || (trails && trail.iter().flatten().any(|age| *age > 0));
+            if let Some(layer) = &mut layer {
+                let next = rule_step(&layer.cells, layer.live, layer.birth, edges, generation);
+                changed |= next != layer.cells;
+                layer.cells = next;
+            }
+            if let Some(species) = &mut species {
+                *species = species_step(&matrix, species, &next, edges);
+            }
+            if trails {age_trail(&mut trail, &matrix, &next);}
+            add_history(&mut envelope, &matrix);
+            add_history(&mut envelope, &next);
+            history.push(&matrix, &next);
+            onion.insert(0, std::mem::replace(&mut matrix, next));
+            onion.truncate(ONION_COLORS.len());
+            flashing = true;
+            generation += 1;
+            simulated += 1;
+            step_time = span.elapsed();
+            changed
+        }}
+    }
+
+    // the grids the undo tree follows, the one being edited then the other layer's
+    macro_rules! layers {
+        () => {
+            match &layer {
+                Some(layer) => vec![&matrix[..], &layer.cells[..]],
+                None => vec![&matrix[..]],
+            }
+        }
+    }
+
+    // after stepping. The grids go on the undo tree, but the stepper already knows
+    // what changed, so it keeps going from there.
+    macro_rules! stepped {
+        () => {
+            dirty = true;
+            undo.record(&layers!(), &labels, generation, &undo_label.0, undo_label.1);
+        }
+    }
+
+    // after anything else that changes what a save would write. Changes to the grids
+    // and labels go on the undo tree then and there.
+    macro_rules! edited {
+        () => {
+            stepper.forget();
+            stepped!();
+        }
+    }
+
+    // back to where the undo tree's moved to. The onion skin, trails, envelope, species
+    // and rewind history were from somewhere else, so they start over.
+    macro_rules! undone {
         () => {
-            matrix = gol_step(&matrix, live, birth);
+            let state = undo.state();
+            matrix = state[0].clone();
+            if let (Some(layer), Some(cells)) = (&mut layer, state.get(1)) {layer.cells = cells.clone()}
+            labels = undo.labels().to_vec();
+            generation = undo.generation();
+            stepper.forget();
+            onion.clear();
+            let (grid_cols, grid_rows) = grid_size!();
+            trail = gen_grid(grid_cols, grid_rows, None);
+            envelope = gen_grid(grid_cols, grid_rows, None);
+            if species.is_some() {species = Some(gen_grid(grid_cols, grid_rows, None));}
+            history.clear();
+            dirty = true;
+        }
+    }
+
+    // show progress in the window title so a backgrounded run can be watched from the tab list
+    macro_rules! set_title {
+        ($pop: expr) => {
+            stdo.queue(terminal::SetTitle(&format!(
+                "cursed_life - gen {} pop {} {}",
+                generation, $pop, rule_str(live, birth)
+            )))?;
         }
     }
 
     // redraw the game and toolbar
     macro_rules! redraw_all {
+        () => {{
+            let span = logfile::span("draw");
+            stdo.queue(Print(SYNC_BEGIN))?;
+            let guide_pos = if guides {grid_pos!(cur_col, cur_row)} else {None};
+            // what the next step would do, without committing it
+            let next = if preview && !playing {Some(rule_step(&matrix, live, birth, edges, generation))} else {None};
+            let ghost_at = ghost.as_ref().map(|g| ghost_origin!(g));
+            let selected = selection!();
+            let play_cursor_pos = if playing && play_cursor {grid_pos!(cur_col, cur_row)} else {None};
+            let label_chars: HashMap<(usize, usize), char> = labels.iter()
+                .flat_map(|(x, y, text)| text.chars().enumerate().map(move |(i, c)| ((x + i, *y), c)))
+                .collect();
+            let overlay = |x: usize, y: usize, alive: bool| {
+                if anchor == Some((x, y)) {
+                    return Some(style(if alive {ch_t} else {ch_f}).on(ANCHOR_COLOR))
+                }
+                if let Some((left, top, right, bottom)) = selected {
+                    if (left..=right).contains(&x) && (top..=bottom).contains(&y) && in_lasso!(x, y) {
+                        return Some(style(if alive {ch_t} else {ch_f}).on(SELECTION_COLOR))
+                    }
+                }
+                if tracing.as_ref().is_some_and(|path| path.contains(&(x, y))) {
+                    return Some(style(if alive {ch_t} else {ch_f}).on(SELECTION_COLOR))
+                }
+                if let Some(c) = label_chars.get(&(x, y)) {
+                    return Some(style(*c).with(LABEL_COLOR))
+                }
+                if play_cursor_pos == Some((x, y)) {
+                    return Some(style(if alive {ch_t} else {ch_f}).on(PLAY_CURSOR_COLOR))
+                }
+                if let (Some(ghost), Some((gx, gy))) = (&ghost, ghost_at) {
+                    let (px, py) = (x as isize - gx, y as isize - gy);
+                    if px >= 0 && py >= 0 && ghost.get(py as usize).and_then(|row| row.get(px as usize)) == Some(&true) {
+                        return Some(style(ch_t).with(GHOST_COLOR))
+                    }
+                }
+                if let (true, Some(mark)) = (compare, &bookmark) {
+                    match (alive, mark[y][x]) {
+                        (true, false) => return Some(style(ch_t).with(ADDED_COLOR)),
+                        (false, true) => return Some(style(ch_t).with(REMOVED_COLOR)),
+                        _ => (),
+                    }
+                }
+                if diff && flashing {
+                    if let Some(prev) = onion.first() {
+                        if prev[y][x] != alive {
+                            return Some(style(if alive {ch_t} else {ch_f}).on(config.diff_color))
+                        }
+                    }
+                }
+                if let Some(next) = &next {
+                    match (alive, next[y][x]) {
+                        (true, false) => return Some(style(ch_t).with(config.die_color)),
+                        (false, true) => return Some(style(ch_t).with(config.born_color)),
+                        _ => (),
+                    }
+                }
+                if let (true, Some(species)) = (alive, &species) {
+                    return Some(style(ch_t).with(SPECIES_COLORS[species[y][x] as usize]))
+                }
+                if marked[y][x] {
+                    return Some(style(if alive {ch_t} else {ch_f}).on(MARKED_COLOR))
+                }
+                if alive {return None}
+                if let (false, Some(layer)) = (dual, &layer) {
+                    if layer.cells[y][x] {return Some(style(ch_t).with(LAYER_COLOR))}
+                }
+                if onion_skin && !playing {
+                    if let Some(i) = onion.iter().position(|gen| gen[y][x]) {
+                        return Some(style(ch_t).with(ONION_COLORS[i]))
+                    }
+                }
+                if trail[y][x] > 0 {
+                    return Some(style(ch_t).with(TRAIL_COLORS[TRAIL_COLORS.len() - trail[y][x] as usize]))
+                }
+                if show_history && envelope[y][x] {
+                    return Some(style(ch_f).on(HISTORY_COLOR))
+                }
+                match guide_pos {
+                    Some((gx, gy)) if gx == x || gy == y => Some(style(GUIDE_CHAR)),
+                    _ => None,
+                }
+            };
+            // inverted swaps the chars, and colors by drawing the overlays in reverse video
+            let flip = inverted != strobing(birth, generation);
+            let (grid_t, grid_f) = if flip {(ch_f, ch_t)} else {(ch_t, ch_f)};
+            let FrameBuffers {text, right, spare, bands, toolbar: last_toolbar} = &mut frame;
+            grid_to_str(text, bands, &matrix, grid_t, grid_f, |x, y, alive| {
+                overlay(x, y, alive).map(|styled| if flip {styled.reverse()} else {styled})
+            });
+            if let (true, Some(layer)) = (dual, &layer) {
+                grid_to_str(right, bands, &layer.cells, grid_t, grid_f, |_, _, _| None);
+                spare.clear();
+                for (l, r) in text.lines().zip(right.lines()) {
+                    spare.push_str(l);
+                    spare.push('|');
+                    spare.push_str(r);
+                    spare.push('\n');
+                }
+                std::mem::swap(text, spare);
+            }
+            if off > 0 {
+                std::mem::swap(text, spare);
+                frame_str(text, spare, if dual {grid_size!().0 * 2 + 1} else {grid_size!().0});
+            }
+            let pos = grid_pos!(cur_col, cur_row).unwrap_or((0, 0));
+            if let Some(text) = &message {logfile::write("message", format_args!("{}", text))}
+            let toolbar = message.take().unwrap_or_else(|| {
+                let mut toolbar = gen_toolbar(ch_t, ch_f, live, birth, config.framerates[framerate], pos);
+                if dirty {toolbar.push_str(" [+]")}
+                if let (true, Some(mark)) = (compare, &bookmark) {
+                    let (added, removed) = matrix.iter().flatten().zip(mark.iter().flatten())
+                        .fold((0, 0), |(a, r), (now, then)| (a + (*now && !*then) as usize, r + (!*now && *then) as usize));
+                    write!(toolbar, " Cmp:+{}-{}", added, removed).unwrap();
+                }
+                match brush {
+                    Some(true) => toolbar.push_str(" Paint"),
+                    Some(false) => toolbar.push_str(" Erase"),
+                    None => (),
+                }
+                if let Some((ax, ay)) = anchor {
+                    let (dx, dy) = (pos.0 as i64 - ax as i64, pos.1 as i64 - ay as i64);
+                    write!(toolbar, " D:{},{} ({:.1})", dx, dy, ((dx * dx + dy * dy) as f64).sqrt()).unwrap();
+                }
+                if let Some(bbox) = selected {
+                    let part = selected_cells!().unwrap_or_default();
+                    let pop = population(&part);
+                    let (width, height) = (bbox.2 - bbox.0 + 1, bbox.3 - bbox.1 + 1);
+                    let area = (bbox.1..=bbox.3).flat_map(|y| (bbox.0..=bbox.2).map(move |x| (x, y)))
+                        .filter(|&(x, y)| in_lasso!(x, y)).count();
+                    write!(toolbar, " Sel:{}x{} Pop:{} ({:.1}%)", width, height, pop,
+                           pop as f64 * 100. / area as f64).unwrap();
+                    // where the live cells are inside it
+                    if let Some((left, top, right, bottom)) = bounding_box(&part) {
+                        write!(toolbar, " Box:{}x{}@{},{}", right - left + 1, bottom - top + 1,
+                               bbox.0 + left, bbox.1 + top).unwrap();
+                    }
+                }
+                toolbar
+            });
+            if toolbar != *last_toolbar {
+                queue_toolbar(&mut stdo, &toolbar, cols, rows)?;
+                *last_toolbar = toolbar;
+            }
+            // the toolbar row is left to itself
+            redraw(&mut stdo, text, cols, rows - 1)?;
+            if show_about && !about.is_empty() {
+                draw_panel(&mut stdo, &about, false, cols, rows)?;
+            }
+            if timing {
+                let mut lines = vec![
+                    format!("step  {}", short_duration(step_time)),
+                    format!("draw  {}", short_duration(draw_time)),
+                    format!("event {}", short_duration(event_time)),
+                ];
+                // the big things we keep, then everything together
+                let grids = grid_bytes(&matrix) + layer.as_ref().map_or(0, |l| grid_bytes(&l.cells))
+                    + species.as_ref().map_or(0, |s| grid_bytes(s));
+                let past = grid_bytes(&trail) + grid_bytes(&envelope) + grid_bytes(&marked)
+                    + onion.iter().map(|g| grid_bytes(g)).sum::<usize>();
+                lines.push(format!("grid  {}", short_bytes(grids)));
+                lines.push(format!("hist  {}", short_bytes(past)));
+                lines.push(format!("rewind {} ({} gens)", short_bytes(history.bytes()), history.len()));
+                lines.push(format!("undo  {} ({} points)", short_bytes(undo.bytes()), undo.len()));
+                if let Some(bytes) = resident_memory() {lines.push(format!("mem   {}", short_bytes(bytes)))}
+                draw_panel(&mut stdo, &lines, true, cols, rows)?;
+            }
+            stdo.queue(Print(SYNC_END))?.flush()?;
+            flashing = false;
+            draw_time = span.elapsed();
+        }}
+    }
+
+    // empty the grid and everything that tracks its history
+    macro_rules! clear {
         () => {
-            redraw(&mut stdo,
-                   &(grid_to_str(&matrix, ch_t, ch_f) +
-                   &gen_toolbar(ch_t, ch_f, live, birth, framerates[framerate])),
-                   cols, rows);
+            let (grid_cols, grid_rows) = grid_size!();
+            matrix = gen_grid(grid_cols, grid_rows, None);
+            trail = gen_grid(grid_cols, grid_rows, None);
+            envelope = gen_grid(grid_cols, grid_rows, None);
+            marked = gen_grid(grid_cols, grid_rows, None);
+            labels.clear();
+            // they were about what's just gone
+            comments.clear();
+            onion.clear();
+            if species.is_some() {species = Some(gen_grid(grid_cols, grid_rows, None));}
+            history.clear();
+            stepper.forget();
+            generation = 0;
         }
     }
 
@@ -266,20 +2139,39 @@ fn main() {
     macro_rules! erase {
         () => {
             let blank = String::from(" ").repeat((cols*rows).into());
-            redraw(&mut stdo, &blank, u16::MAX, u16::MAX);
+            redraw(&mut stdo, &blank, u16::MAX, u16::MAX)?;
+            frame.toolbar.clear();
         }
     }
 
     // update cols rows, resize grid, erase!() and redraw_all!().
     macro_rules! resize {
         () => {
-            let (new_cols, new_rows) = terminal::size().unwrap();
+            let (new_cols, new_rows) = terminal::size()?;
             resize!(new_cols, new_rows);
             };
         ($new_cols: expr, $new_rows: expr) => {
-            cols = $new_cols;
-            rows = $new_rows;
-            matrix = gen_grid(cols as usize, rows as usize - 1, Some(matrix));
+            let (new_cols, new_rows) = wait_for_size(&mut stdo, $new_cols, $new_rows)?;
+            logfile::write("resize", format_args!("{}x{}", new_cols, new_rows));
+            cols = new_cols;
+            rows = new_rows;
+            off = if border && cols > 2 && rows > 3 {1} else {0};
+            let (grid_cols, grid_rows) = grid_size!();
+            matrix = gen_grid(grid_cols, grid_rows, Some(matrix));
+            // cells cut off and grown back again would go unnoticed
+            stepper.forget();
+            trail = gen_grid(grid_cols, grid_rows, Some(trail));
+            envelope = gen_grid(grid_cols, grid_rows, Some(envelope));
+            marked = gen_grid(grid_cols, grid_rows, Some(marked));
+            bookmark = bookmark.map(|mark| gen_grid(grid_cols, grid_rows, Some(mark)));
+            undo.resize(grid_cols, grid_rows);
+            onion = onion.into_iter().map(|gen| gen_grid(grid_cols, grid_rows, Some(gen))).collect();
+            if let Some(layer) = &mut layer {
+                layer.cells = gen_grid(grid_cols, grid_rows, Some(std::mem::take(&mut layer.cells)));
+            }
+            if let Some(species) = &mut species {
+                *species = gen_grid(grid_cols, grid_rows, Some(std::mem::take(species)));
+            }
             // if you  don't erase chars can get left over in lower-right corner.
             erase!();
             redraw_all!();
@@ -289,103 +2181,717 @@ fn main() {
     // erase!(), write HELP_TEXT, wait for keycode 'h', redraw_all!()
     macro_rules! show_help {
         () => {
-            stdo.queue(cursor::Hide).unwrap();
+            stdo.queue(cursor::Hide)?;
             erase!();
-            redraw(&mut stdo, HELP_TEXT, cols, rows);
+            redraw(&mut stdo, HELP_TEXT, cols, rows)?;
             loop {
-                match get_event(None) {
+                match event!(None) {
                     Some(KE!('h')) => break,
+                    Some(KE!('z', KeyModifiers::CONTROL)) => {
+                        suspend()?;
+                        stdo.queue(cursor::Hide)?;
+                        let (ncols, nrows) = terminal::size()?;
+                        let (ncols, nrows) = wait_for_size(&mut stdo, ncols, nrows)?;
+                        cols = ncols;
+                        rows = nrows;
+                        erase!();
+                        redraw(&mut stdo, HELP_TEXT, cols, rows)?;
+                    },
                     Some(Event::Resize(ncols, nrows)) => {
+                        let (ncols, nrows) = wait_for_size(&mut stdo, ncols, nrows)?;
                         cols = ncols;
                         rows = nrows;
                         erase!();
-                        redraw(&mut stdo, HELP_TEXT, cols, rows);
+                        redraw(&mut stdo, HELP_TEXT, cols, rows)?;
                     },
                     _ => (),
                 }
             }
-            stdo.queue(cursor::Show).unwrap();
+            stdo.queue(cursor::Show)?;
             // in case the window resized.
             resize!();
         }
     }
 
+    // don't let cursor into toolbar or onto the border
+    macro_rules! clamp_cursor {
+        () => {
+            let (grid_cols, grid_rows) = grid_size!();
+            cur_col = cur_col.max(off).min(off + grid_cols as u16 - 1);
+            cur_row = cur_row.max(off).min(off + grid_rows as u16 - 1);
+        }
+    }
+
+    // rule and framerate keys, for both editing and playback. true if it was one of them.
+    macro_rules! tweak {
+        ($event: expr) => {
+            match $event {
+                Some(KE!('-')) => {live = (live-1).max(0); edited!(); true},
+                Some(KE!('=')) => {live = (live+1).min(9); edited!(); true},
+                Some(KE!('[')) => {birth = (birth-1).max(0); edited!(); true},
+                Some(KE!(']')) => {birth = (birth+1).min(9); edited!(); true},
+                Some(KE!(',')) => {framerate = framerate.saturating_sub(1); true},
+                Some(KE!('.')) => {framerate = (framerate+1).min(config.framerates.len()-1); true},
+                _ => false,
+            }
+        }
+    }
+
+    // reads a line of text in place of the toolbar. None if escaped.
+    // With paths true, tab completes file and directory names.
+    macro_rules! prompt {
+        ($label: expr) => {prompt!($label, false)};
+        ($label: expr, $paths: expr) => {{
+            let mut text = String::new();
+            loop {
+                message = Some(format!("{}{}", $label, text));
+                redraw_all!();
+                stdo.execute(cursor::MoveTo(((($label.len() + text.len()) as u16).min(cols - 1)), rows - 1))?;
+                match read_event!(None) {
+                    Some(Event::Key(KeyEvent{code: KeyCode::Enter, ..})) => break Some(text),
+                    Some(Event::Key(KeyEvent{code: KeyCode::Esc, ..})) => break None,
+                    Some(Event::Key(KeyEvent{code: KeyCode::Backspace, ..})) => {text.pop();},
+                    Some(Event::Key(KeyEvent{code: KeyCode::Tab, ..})) if $paths => text = files::complete(&text),
+                    Some(Event::Key(KeyEvent{code: KeyCode::Char(c), ..})) => text.push(c),
+                    Some(Event::Resize(c, r)) => {resize!(c, r);},
+                    _ => (),
+                }
+            }
+        }}
+    }
+
+    // whether to go ahead with a key that throws work away, the way the config says to ask
+    macro_rules! confirm {
+        ($key: expr, $what: expr) => {
+            match config.confirm {
+                Confirm::Twice => matches!(event!(None), Some(Event::Key(KeyEvent{code: KeyCode::Char(c), ..})) if c == $key),
+                Confirm::Ask => {
+                    message = Some(format!("{}? y/n", $what));
+                    redraw_all!();
+                    let yes = matches!(event!(None), Some(KE!('y')));
+                    redraw_all!();
+                    yes
+                },
+                Confirm::Off => true,
+            }
+        }
+    }
+
+    // saved as LifeHistory when there's any to keep, which only goes with Conway's rules
+    macro_rules! life_history {
+        () => {(live, birth) == (2, 3) && (show_history || marked.iter().flatten().any(|m| *m))}
+    }
+
+    // when a file last changed, for --watch. Our own saves count as seen.
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut last_modified = modified(&save_path);
+
+    // writes what's in bbox to save_path, or an empty pattern for None. true if it worked.
+    macro_rules! save {
+        ($bbox: expr) => {{
+            let bbox: Option<(usize, usize, usize, usize)> = $bbox;
+            let (grid_cols, grid_rows) = grid_size!();
+            let cut = |grid: &[Vec<bool>]| bbox.map_or_else(Vec::new, |bbox| crop(grid, bbox));
+            // along with the top-left cell, for #CXRLE
+            let (left, top) = bbox.map_or((0, 0), |bbox| (bbox.0, bbox.1));
+            let mut pattern = Pattern::new(cut(&matrix), Some(rule_str(live, birth)));
+            if life_history!() {
+                pattern.history = Some(cut(&envelope));
+                pattern.marked = Some(cut(&marked));
+            }
+            pattern.labels = labels.iter()
+                .map(|(x, y, text)| (*x as i64 - left as i64, *y as i64 - top as i64, text.clone()))
+                .collect();
+            pattern.pos = Some((left as i64 - (grid_cols / 2) as i64, top as i64 - (grid_rows / 2) as i64));
+            pattern.generation = Some(generation);
+            pattern.comments = comments.clone();
+            if config.sign_saves && !comments.iter().any(|c| c.ends_with(SIGNATURE)) {
+                pattern.comments.push(format!("#C {}", SIGNATURE));
+            }
+            match pattern::save(&save_path, &pattern) {
+                Ok(()) => {
+                    let _ = recent::add(&save_path);
+                    // or --watch would load it straight back, starting the generations over
+                    last_modified = modified(&save_path);
+                    dirty = false;
+                    message = Some(format!("saved {}x{} to {}", pattern.width(), pattern.height(), save_path.display()));
+                    true
+                },
+                Err(e) => {
+                    message = Some(format!("failed to save {}", e));
+                    false
+                },
+            }
+        }}
+    }
+
+    // a pattern file's cells, remembered as the last and a recent one. None with a message if it's no good.
+    macro_rules! load_cells {
+        ($path: expr) => {{
+            let path: &Path = $path;
+            match pattern::load(path) {
+                Ok(mut pattern) if pattern.width() > 0 => {
+                    let _ = recent::add(path);
+                    // stamped in, so a checkpoint's edges don't apply
+                    headless::take_edges(&mut pattern);
+                    about = metadata(&pattern);
+                    show_about = true;
+                    last_opened = Some(pattern.cells.clone());
+                    Some(pattern.cells)
+                },
+                Ok(_) => {message = Some("pattern is empty".to_string()); None},
+                Err(e) => {message = Some(Error::Pattern(e).to_string()); None},
+            }
+        }}
+    }
+
+    // asks for a pattern file. Nothing typed means the last one opened.
+    macro_rules! open_pattern {
+        ($label: expr) => {{
+            let path = prompt!($label, true);
+            message = None;
+            match path {
+                Some(path) if !path.trim().is_empty() => load_cells!(Path::new(path.trim())),
+                Some(_) if last_opened.is_none() => {message = Some("no pattern opened yet".to_string()); None},
+                Some(_) => last_opened.clone(),
+                None => None,
+            }
+        }}
+    }
+
+    // lets the ghost be moved around, then placed at the cursor or dropped
+    macro_rules! place_ghost {
+        () => {
+            if ghost.is_some() {
+                message = Some("wasd/click to move, space/enter to place, esc to cancel".to_string());
+            }
+            redraw_all!();
+            while ghost.is_some() {
+                stdo.execute(cursor::MoveTo(cur_col, cur_row))?;
+                let place = match event!(None) {
+                    Some(KE!('w')) => {cur_row = cur_row.saturating_sub(1); false},
+                    Some(KE!('a')) => {cur_col = cur_col.saturating_sub(1); false},
+                    Some(KE!('s')) => {cur_row += 1; false},
+                    Some(KE!('d')) => {cur_col += 1; false},
+                    Some(Event::Mouse(MouseEvent::Down(_, col, row, _))) => {
+                        cur_col = col;
+                        cur_row = row;
+                        true
+                    },
+                    Some(KE!(' ')) | Some(Event::Key(KeyEvent{code: KeyCode::Enter, ..})) => true,
+                    Some(Event::Key(KeyEvent{code: KeyCode::Esc, ..})) => {ghost = None; false},
+                    Some(Event::Resize(c, r)) => {resize!(c, r); false},
+                    _ => false,
+                };
+                clamp_cursor!();
+                if place {
+                    if let Some(cells) = ghost.take() {
+                        let (col, row) = ghost_origin!(cells);
+                        stamp(&mut matrix, &cells, col, row);
+                        edited!();
+                    }
+                }
+                redraw_all!();
+            }
+        }
+    }
+
     // start off with control screen. First impressions are important.
-    show_help!();
+    // Demos go straight to playing though, and returning players can skip it.
+    if options.demo.is_some() || options.skip_help || !config.help_at_start {resize!();} else {show_help!();}
+
+    // the grid as a --gif frame, if it's recording. A failed write stops it.
+    macro_rules! record_frame {
+        () => {
+            if let Some((gif, crop)) = &mut recording {
+                let view = crop.map_or_else(|| matrix.clone(), |(bbox, margin)| crop_margin(&matrix, bbox, margin));
+                if let Err(e) = gif.frame(&view, gif_delay(config.framerates[framerate])) {
+                    message = Some(format!("stopped recording, {}", e));
+                    recording = None;
+                }
+            }
+        }
+    }
+
+    // replace the grid with a pattern in the middle of it
+    macro_rules! place {
+        ($pattern: expr) => {
+            let mut pattern: Pattern = $pattern;
+            clear!();
+            // a checkpoint's edges, unless --edges said otherwise
+            if let (None, Some(e)) = (options.edges, headless::take_edges(&mut pattern)) {edges = e}
+            about = metadata(&pattern);
+            comments = pattern.comments.clone();
+            let (grid_cols, grid_rows) = grid_size!();
+            let (col, row) = pattern_origin(&pattern, grid_cols, grid_rows);
+            stamp(&mut matrix, &pattern.cells, col, row);
+            if let Some(history) = &pattern.history {
+                stamp(&mut envelope, history, col, row);
+                show_history = true;
+            }
+            if let Some(marks) = &pattern.marked {stamp(&mut marked, marks, col, row);}
+            labels = pattern.labels.iter()
+                .map(|(x, y, text)| (col + *x as isize, row + *y as isize, text.clone()))
+                .filter(|(x, y, _)| *x >= 0 && *y >= 0)
+                .map(|(x, y, text)| (x as usize, y as usize, text))
+                .collect();
+            if let Some(gen) = pattern.generation {generation = gen;}
+            if pattern.width() > grid_cols || pattern.height() > grid_rows {
+                message = Some(format!("pattern is {}x{}, larger than the {}x{} grid. Edges clipped",
+                                       pattern.width(), pattern.height(), grid_cols, grid_rows));
+            }
+            if let Some(rule) = pattern.rule {
+                match parse_rule(&rule) {
+                    Some((l, b)) => {live = l; birth = b;},
+                    None => message = Some(format!("rule {} not supported, using {}", rule, rule_str(live, birth))),
+                }
+            }
+        }
+    }
+
+    // reload the config, and with --watch the pattern, whenever their modified times change.
+    let mut config_modified = config_path.as_deref().and_then(modified);
+    let mut last_check = Instant::now();
+    macro_rules! check_watch {
+        () => {
+            if last_check.elapsed() >= WATCH_INTERVAL {
+                last_check = Instant::now();
+                let now_modified = config_path.as_deref().and_then(modified);
+                if now_modified != config_modified {
+                    config_modified = now_modified;
+                    match config::load(config_path.as_deref().unwrap()) {
+                        Ok(new) => {
+                            // keep going at about the same speed
+                            framerate = nearest_framerate(&new.framerates, config.framerates[framerate]);
+                            ch_t = new.ch_t;
+                            ch_f = new.ch_f;
+                            history.set_cap(new.history_cap);
+                            undo.set_cap(new.history_cap);
+                            let resized = new.max_size != config.max_size;
+                            config = new;
+                            if resized {resize!();}
+                            message = Some("config reloaded".to_string());
+                        },
+                        Err(e) => message = Some(format!("bad config {}", e)),
+                    }
+                    redraw_all!();
+                }
+                let now_modified = modified(&save_path);
+                if options.watch && now_modified != last_modified {
+                    last_modified = now_modified;
+                    match pattern::load(&save_path) {
+                        Ok(pattern) => {
+                            // anything played since the last record goes on first, as its own point
+                            undo.record(&layers!(), &labels, generation, &undo_label.0, undo_label.1);
+                            place!(pattern);
+                            dirty = false;
+                            undo.record(&layers!(), &labels, generation, "reload", false);
+                        },
+                        Err(e) => message = Some(Error::Pattern(e).to_string()),
+                    }
+                    redraw_all!();
+                }
+            }
+        }
+    }
+
+    // the about panel's filled in when it's placed
+    show_about = pattern.is_some();
+
+    // with nothing given, maybe pick up where the last session left off
+    let (pattern, settings) = match pattern {
+        None if config.restore_session => session::load().map_or((None, HashMap::new()), |(p, s)| (Some(p), s)),
+        pattern => (pattern, HashMap::new()),
+    };
+
+    // place a pattern from the command line
+    if let Some(pattern) = pattern {
+        place!(pattern);
+        if let Some(e) = settings.get("edges").and_then(|e| Edges::parse(e)) {edges = e}
+        if let Some(fps) = settings.get("framerate").and_then(|f| f.parse().ok()) {
+            framerate = nearest_framerate(&config.framerates, fps);
+        }
+        let chars = settings.get("chars").and_then(|c| c.split_once(' '))
+            .and_then(|(t, f)| Some((char::from_u32(t.parse().ok()?)?, char::from_u32(f.parse().ok()?)?)));
+        if let Some((t, f)) = chars.filter(|(t, f)| valid_chars(*t) && valid_chars(*f) && t != f) {
+            ch_t = t;
+            ch_f = f;
+        }
+        redraw_all!();
+    }
+
+    // a key to act on before reading any
+    let mut pending = options.demo.as_ref()
+        .map(|_| Event::Key(KeyEvent{code: KeyCode::Char('f'), modifiers: KeyModifiers::NONE}));
+
+    // where the cursor was last drawn, to catch moves
+    let mut drawn_pos = (cur_col, cur_row);
+
+    undo.reset(&layers!(), &labels, generation);
 
     // main loop
     loop {
-        let (mut cur_col, mut cur_row) = cursor::position().unwrap();
+        check_watch!();
+
+        clamp_cursor!();
 
-        // don't let cursor into toolbar
-        if cur_row > rows - 2 {
-            stdo.execute(cursor::MoveUp(1)).unwrap();
-            // no way to update mutables from tuple?
-            let (ncur_col, ncur_row) = cursor::position().unwrap();
-            cur_col = ncur_col;
-            cur_row = ncur_row;
+        // update the coordinate readout and guides
+        if (cur_col, cur_row) != drawn_pos {
+            if let (Some(alive), Some((x, y))) = (brush, grid_pos!(cur_col, cur_row)) {
+                matrix[y][x] = alive;
+                edited!();
+            }
+            if let (Some(path), Some(pos)) = (&mut tracing, grid_pos!(cur_col, cur_row)) {
+                path.push(pos);
+            }
+            redraw_all!();
+            drawn_pos = (cur_col, cur_row);
+        }
+        stdo.execute(cursor::MoveTo(cur_col, cur_row))?;
+
+        // wake up now and then to check for changed files
+        let event = match pending.take() {
+            Some(event) => Some(event),
+            None => event!(Some(WATCH_INTERVAL)),
+        };
+        if let Some(event) = &event {undo_label = edit_label(event)}
+        // the info panel takes the first key, just to go away
+        if show_about && !about.is_empty() && matches!(event, Some(Event::Key(_))) {
+            show_about = false;
+            redraw_all!();
+            continue
+        }
+        if tweak!(&event) {
+            redraw_all!();
+            continue
         }
+        let arrow = match &event {
+            Some(Event::Key(KeyEvent{code: KeyCode::Up, ..})) => Some((0, -1)),
+            Some(Event::Key(KeyEvent{code: KeyCode::Down, ..})) => Some((0, 1)),
+            Some(Event::Key(KeyEvent{code: KeyCode::Left, ..})) => Some((-1, 0)),
+            Some(Event::Key(KeyEvent{code: KeyCode::Right, ..})) => Some((1, 0)),
+            _ => None,
+        };
+        if arrow.is_none() && event.is_some() {floating = None}
+        match event {
+            // nudge the selection a cell, uncovering what was under it
+            _ if arrow.is_some() => {
+                let (dx, dy) = arrow.unwrap_or((0, 0));
+                let (grid_cols, grid_rows) = grid_size!();
+                let moved = selection.filter(|_| selecting.is_none()).and_then(|(left, top, right, bottom)| {
+                    let (left, top) = (left.checked_add_signed(dx)?, top.checked_add_signed(dy)?);
+                    let (right, bottom) = (right.checked_add_signed(dx)?, bottom.checked_add_signed(dy)?);
+                    Some((left, top, right, bottom)).filter(|_| right < grid_cols && bottom < grid_rows)
+                });
+                if let Some((left, top, right, bottom)) = moved {
+                    let contents = selected_cells!().unwrap_or_default();
+                    let background = floating.take().unwrap_or_else(|| {
+                        let mut background = matrix.clone();
+                        let (old_left, old_top) = (left.wrapping_add_signed(-dx), top.wrapping_add_signed(-dy));
+                        for (y, row) in background.iter_mut().enumerate().skip(old_top).take(bottom - top + 1) {
+                            for (x, cell) in row.iter_mut().enumerate().skip(old_left).take(right - left + 1) {
+                                if in_lasso!(x, y) {*cell = false}
+                            }
+                        }
+                        background
+                    });
+                    if let Some(mask) = &mut lasso {
+                        let mut shifted = gen_grid(grid_cols, grid_rows, None);
+                        for (y, row) in mask.iter().enumerate() {
+                            for (x, _) in row.iter().enumerate().filter(|(_, m)| **m) {
+                                if let (Some(x), Some(y)) = (x.checked_add_signed(dx), y.checked_add_signed(dy)) {
+                                    if x < grid_cols && y < grid_rows {shifted[y][x] = true}
+                                }
+                            }
+                        }
+                        *mask = shifted;
+                    }
+                    matrix = background.clone();
+                    for (y, row) in contents.iter().enumerate() {
+                        for (x, alive) in row.iter().enumerate() {
+                            if in_lasso!(left + x, top + y) {matrix[top + y][left + x] = *alive}
+                        }
+                    }
+                    selection = Some((left, top, right, bottom));
+                    floating = Some(background);
+                    edited!();
+                }
+                redraw_all!();
+            },
+
+            // save somewhere else from now on. The extension picks the format.
+            // Ahead of movement, which would take it for 's'.
+            Some(KE!('s', KeyModifiers::CONTROL)) => {
+                match prompt!("save as, .rle or .cells (tab completes): ", true) {
+                    Some(path) if !path.trim().is_empty() => {
+                        save_path = PathBuf::from(path.trim());
+                        if save_path.is_dir() {save_path.push(DEFAULT_SAVE)}
+                        last_modified = modified(&save_path);
+                        // on to the usual save, to pick the whole grid or just the live cells
+                        pending = Some(Event::Key(KeyEvent{code: KeyCode::Char('S'), modifiers: KeyModifiers::SHIFT}));
+                    },
+                    _ => message = None,
+                }
+                redraw_all!();
+            }
 
-        match get_event(None) {
             // movement
-            Some(KE!('w')) => {stdo.execute(cursor::MoveUp(1)).unwrap();},
-            Some(KE!('a')) => {stdo.execute(cursor::MoveLeft(1)).unwrap();},
-            Some(KE!('s')) => {stdo.execute(cursor::MoveDown(1)).unwrap();},
-            Some(KE!('d')) => {stdo.execute(cursor::MoveRight(1)).unwrap();},
+            Some(KE!('w')) => cur_row = cur_row.saturating_sub(1),
+            Some(KE!('a')) => cur_col = cur_col.saturating_sub(1),
+            Some(KE!('s')) => cur_row += 1,
+            Some(KE!('d')) => cur_col += 1,
 
             // toggle point
             Some(KE!(' ')) => {
-                grid_toggle(&mut matrix, cur_col as usize, cur_row as usize);
-                redraw_all!();
-            },
-
-            // change rules
-            Some(KE!('-')) => {
-                live = (live-1).max(0);
+                let (col, row) = ((cur_col - off) as usize, (cur_row - off) as usize);
+                match &mut species {
+                    // dead, red, blue, dead...
+                    Some(species) if matrix[row][col] && !species[row][col] => species[row][col] = true,
+                    Some(species) => {
+                        species[row][col] = false;
+                        grid_toggle(&mut matrix, col, row);
+                    },
+                    None => grid_toggle(&mut matrix, col, row),
+                }
+                edited!();
                 redraw_all!();
             },
-            Some(KE!('=')) => {
-                live = (live+1).min(9);
+
+            // undo and redo whatever last changed the grid, edits and steps alike.
+            // Ahead of rewinding, which would take ctrl-r for 'r'.
+            Some(KE!('r', KeyModifiers::CONTROL)) => {
+                if undo.redo() {
+                    undone!();
+                    message = Some(format!("redid to generation {}", generation));
+                } else {
+                    message = Some("nothing to redo here".to_string());
+                }
                 redraw_all!();
-            },
-            Some(KE!('[')) => {
-                birth = (birth-1).max(0);
+            }
+            Some(KE!('u', KeyModifiers::CONTROL)) => {
+                // the grid follows the choice, to see where each point is
+                let start = undo.current();
+                stdo.queue(cursor::Hide)?;
+                loop {
+                    let points = undo.lines();
+                    let choice = points.iter().position(|(id, _)| *id == undo.current()).unwrap_or(0);
+                    let view = (rows as usize).saturating_sub(6).max(1);
+                    let first = (choice + 1).saturating_sub(view);
+                    let mut lines = vec!["undo tree. ws to go, enter to stay, esc to go back".to_string(), String::new()];
+                    lines.extend(points[first..].iter().take(view).enumerate()
+                        .map(|(i, (_, text))| format!("{} {}", if first + i == choice {'>'} else {' '}, text)));
+                    redraw_all!();
+                    draw_panel(&mut stdo, &lines, false, cols, rows)?;
+                    let target = match event!(None) {
+                        Some(KE!('w')) | Some(Event::Key(KeyEvent{code: KeyCode::Up, ..})) => choice.checked_sub(1),
+                        Some(KE!('s')) | Some(Event::Key(KeyEvent{code: KeyCode::Down, ..})) => Some(choice + 1),
+                        Some(KE!(' ')) | Some(Event::Key(KeyEvent{code: KeyCode::Enter, ..})) => break,
+                        Some(Event::Key(KeyEvent{code: KeyCode::Esc, ..})) => {
+                            undo.jump(start);
+                            undone!();
+                            break
+                        },
+                        Some(Event::Resize(ncols, nrows)) => {resize!(ncols, nrows); None},
+                        _ => None,
+                    };
+                    if let Some((id, _)) = target.and_then(|t| points.get(t)) {
+                        undo.jump(*id);
+                        undone!();
+                    }
+                }
+                stdo.queue(cursor::Show)?;
                 redraw_all!();
-            },
-            Some(KE!(']')) => {
-                birth = (birth+1).min(9);
+            }
+            Some(KE!('u')) => {
+                if undo.undo() {
+                    undone!();
+                    message = Some(format!("undid to generation {}, ctrl-r redoes", generation));
+                } else {
+                    message = Some("nothing left to undo".to_string());
+                }
                 redraw_all!();
-            },
+            }
 
             // frame-advance
             Some(KE!('e')) =>  {
                 step!();
+                stepped!();
                 redraw_all!();
             }
 
-            // change framerate
-            Some(KE!(',')) => {
-                framerate = (framerate as i32-1).max(0) as usize;
+            // and back
+            Some(KE!('r')) => {
+                if history.back(&mut matrix) {
+                    generation = generation.saturating_sub(1);
+                    // the onion skin and trails would be from the future
+                    onion.clear();
+                    let (grid_cols, grid_rows) = grid_size!();
+                    trail = gen_grid(grid_cols, grid_rows, None);
+                    edited!();
+                    message = Some(format!("rewound to generation {}, {} more kept", generation, history.len()));
+                } else {
+                    message = Some("no more history to rewind".to_string());
+                }
                 redraw_all!();
             }
-            Some(KE!('.')) => {
-                framerate = (framerate+1).min(framerates.len()-1);
+
+            // scrub through the generations 'r' could rewind to, on a bar in the toolbar
+            Some(KE!('k')) => {
+                let kept = history.len();
+                if kept == 0 {
+                    message = Some("no history to scrub through yet".to_string());
+                    redraw_all!();
+                } else {
+                    let newest = matrix.clone();
+                    // the onion skin would be from the newest
+                    let skin = std::mem::take(&mut onion);
+                    // steps back from the newest
+                    let mut back = 0;
+                    stdo.queue(cursor::Hide)?;
+                    let keep = loop {
+                        let (left, right) = (format!("gen {} ", generation - back as u64), " enter keeps, esc");
+                        let width = (cols as usize).saturating_sub(left.len() + right.len() + 1);
+                        let at = ((kept - back) * width.saturating_sub(1)).checked_div(kept).unwrap_or(0);
+                        let bar: String = (0..width).map(|i| if i == at {'█'} else {'─'}).collect();
+                        message = Some(format!("{}{}{}", left, bar, right));
+                        redraw_all!();
+                        let tenth = kept.div_ceil(10);
+                        let to = match event!(None) {
+                            Some(KE!('a')) | Some(Event::Key(KeyEvent{code: KeyCode::Left, ..})) => back + 1,
+                            Some(KE!('d')) | Some(Event::Key(KeyEvent{code: KeyCode::Right, ..})) => back.saturating_sub(1),
+                            Some(KE!('w')) | Some(Event::Key(KeyEvent{code: KeyCode::PageUp, ..})) => back + tenth,
+                            Some(KE!('s')) | Some(Event::Key(KeyEvent{code: KeyCode::PageDown, ..})) => back.saturating_sub(tenth),
+                            Some(Event::Key(KeyEvent{code: KeyCode::Home, ..})) => kept,
+                            Some(Event::Key(KeyEvent{code: KeyCode::End, ..})) => 0,
+                            Some(KE!(' ')) | Some(Event::Key(KeyEvent{code: KeyCode::Enter, ..})) => break true,
+                            Some(Event::Key(KeyEvent{code: KeyCode::Esc, ..})) => break false,
+                            // the steps wouldn't fit a resized grid
+                            Some(Event::Resize(ncols, nrows)) => {
+                                matrix = newest.clone();
+                                resize!(ncols, nrows);
+                                back = 0;
+                                break false
+                            },
+                            _ => back,
+                        }.min(kept);
+                        while back < to {history.flip(&mut matrix, back); back += 1}
+                        while back > to {back -= 1; history.flip(&mut matrix, back);}
+                    };
+                    if keep && back > 0 {
+                        history.truncate(back);
+                        generation -= back as u64;
+                        let (grid_cols, grid_rows) = grid_size!();
+                        trail = gen_grid(grid_cols, grid_rows, None);
+                        edited!();
+                        message = Some(format!("back to generation {}, {} more kept", generation, history.len()));
+                    } else {
+                        if back > 0 {matrix = newest}
+                        onion = skin;
+                    }
+                    stdo.queue(cursor::Show)?;
+                    redraw_all!();
+                }
+            }
+
+            // straight to a generation, stepping without drawing on the way.
+            // Or back to one, if it's still kept for rewinding.
+            Some(KE!('J')) => {
+                match prompt!("jump to generation: ").map(|n| n.trim().parse::<u64>()) {
+                    Some(Ok(target)) if target < generation => {
+                        let back = (generation - target) as usize;
+                        if back > history.len() {
+                            message = Some(format!("generation {} isn't kept any more, {} is the oldest",
+                                                   target, generation - history.len() as u64));
+                        } else {
+                            for step in 0..back {history.flip(&mut matrix, step);}
+                            history.truncate(back);
+                            generation = target;
+                            onion.clear();
+                            let (grid_cols, grid_rows) = grid_size!();
+                            trail = gen_grid(grid_cols, grid_rows, None);
+                            edited!();
+                            message = Some(format!("back to generation {}, {} more kept", generation, history.len()));
+                        }
+                    },
+                    Some(Ok(target)) => {
+                        let (start, timer) = (generation, Instant::now());
+                        let mut shown = Instant::now();
+                        stdo.queue(cursor::Hide)?;
+                        while generation < target {
+                            step!();
+                            if shown.elapsed() >= JUMP_PROGRESS {
+                                shown = Instant::now();
+                                let done = (generation - start) * 100 / (target - start);
+                                queue_toolbar(&mut stdo, &format!("jumping to generation {}: {}%, esc stops", target, done), cols, rows)?;
+                                stdo.flush()?;
+                                if let Some(Event::Key(KeyEvent{code: KeyCode::Esc, ..})) = event!(Some(Duration::ZERO)) {break}
+                            }
+                        }
+                        // the usual toolbar goes back over the progress
+                        frame.toolbar.clear();
+                        stdo.queue(cursor::Show)?;
+                        stepped!();
+                        message = Some(format!("generation {} in {:.1}s", generation, timer.elapsed().as_secs_f64()));
+                    },
+                    Some(Err(_)) => message = Some("generation must be a whole number".to_string()),
+                    None => message = None,
+                }
                 redraw_all!();
             }
 
             // play. also logs performance if -l passed.
             Some(KE!('f')) =>  {
-                stdo.queue(cursor::Hide).unwrap();
+                stdo.queue(cursor::Hide)?;
+                playing = true;
                 let min_delay = Duration::from_micros(0);
-                let max_delay = Duration::from_secs_f64(1./framerates[framerate]);
                 let mut delta: Duration;
                 // for framerate average. only used if log
                 let mut frames = 0.;
                 let total_timer = Instant::now();
+                // the recording starts where playback first did, cropped to the live cells then
+                if let Some(path) = gif_path.take() {
+                    let crop = bounding_box(&matrix).zip(config.export_crop);
+                    let (gif_cols, gif_rows) = match crop {
+                        Some(((left, top, right, bottom), margin)) => (right - left + 1 + margin * 2, bottom - top + 1 + margin * 2),
+                        None => grid_size!(),
+                    };
+                    match std::fs::File::create(&path).and_then(|file| image::Gif::new(BufWriter::new(file), gif_cols, gif_rows)) {
+                        Ok(gif) => {
+                            recording = Some((gif, crop));
+                            record_frame!();
+                        },
+                        Err(e) => message = Some(format!("failed to record {}: {}", path.display(), e)),
+                    }
+                }
 
                 loop {
                     let delta_timer = Instant::now();
-                    match get_event(Some(min_delay)) {
+                    check_watch!();
+                    let max_delay = Duration::from_secs_f64(1./config.framerates[framerate]);
+                    let event = event!(Some(min_delay));
+                    // handling only, not the wait for it
+                    let event_timer = Instant::now();
+                    // rules and framerate can change without stopping
+                    if tweak!(&event) {
+                        redraw_all!();
+                        continue
+                    }
+                    // the cursor keeps moving to line up the next edit, stepping like any other key
+                    let drawn_pos = (cur_col, cur_row);
+                    match &event {
+                        Some(KE!('w')) => cur_row = cur_row.saturating_sub(1),
+                        Some(KE!('a')) => cur_col = cur_col.saturating_sub(1),
+                        Some(KE!('s')) => cur_row += 1,
+                        Some(KE!('d')) => cur_col += 1,
+                        Some(Event::Mouse(MouseEvent::Down(_, col, row, _))) => {
+                            cur_col = *col;
+                            cur_row = *row;
+                        },
+                        _ => (),
+                    }
+                    clamp_cursor!();
+                    event_time = event_timer.elapsed();
+                    match event {
 
                         // if 'f', break
                         Some(Event::Key(
@@ -397,72 +2903,111 @@ fn main() {
                         // if resize, resize!
                         Some(Event::Resize(c, r)) => {resize!(c, r);},
 
+                        Some(KE!('z', KeyModifiers::CONTROL)) => {
+                            suspend()?;
+                            stdo.queue(cursor::Hide)?;
+                            resize!();
+                        },
+
                         // else, iter.
                         _ => {
                             // if statement cause log is messy. don't want perpetually growing
                             // vectors in normal play. also theoretically boost performance by not
                             // making so many new timers every frame
-                            if log {
+                            // settled grids don't need drawing again
+                            let changed = if log {
                                 let step_timer = Instant::now();
-                                step!();
+                                let changed = step!();
                                 step_times.push(step_timer.elapsed().as_micros());
-                                let draw_timer = Instant::now();
-                                redraw_all!();
-                                draw_times.push(draw_timer.elapsed().as_micros());
+                                if changed || (cur_col, cur_row) != drawn_pos {
+                                    let draw_timer = Instant::now();
+                                    redraw_all!();
+                                    draw_times.push(draw_timer.elapsed().as_micros());
+                                }
                                 frames += 1.;
+                                changed
                             } else {
-                                step!();
+                                let changed = step!();
+                                if changed || (cur_col, cur_row) != drawn_pos {redraw_all!();}
+                                changed
+                            };
+                            let pop = population(&matrix);
+                            set_title!(pop);
+                            if options.checkpoint_every.is_some_and(|every| generation.is_multiple_of(every)) {
+                                if let Err(e) = headless::write_checkpoint(&options.checkpoint, &matrix, generation, live, birth, edges) {
+                                    message = Some(format!("failed to write checkpoint {}", e));
+                                    break
+                                }
+                            }
+                            if generation.is_multiple_of(options.gif_every) {record_frame!();}
+                            if options.snapshot_every.is_some_and(|every| generation.is_multiple_of(every)) {
+                                if snapshot_box.is_none() {snapshot_box = bounding_box(&matrix)}
+                                let crop = snapshot_box.zip(config.export_crop);
+                                if let Err(e) = write_snapshot(&options.snapshot_dir, options.snapshot_png, crop, &matrix, generation, &rule_str(live, birth)) {
+                                    message = Some(format!("failed to write snapshot {}", e));
+                                    break
+                                }
+                            }
+                            // at slow speeds, show the changes for a moment before the plain frame
+                            if diff && changed && max_delay > DIFF_FLASH * 2 {
+                                std::thread::sleep(DIFF_FLASH);
                                 redraw_all!();
                             }
+                            // nothing left to watch. ring the bell and drop back to editing,
+                            // unless more is on the way
+                            if pop == 0 && options.inject.is_none() && cosmic == 0. && layer.as_ref().is_none_or(|l| population(&l.cells) == 0) {
+                                stdo.queue(Print('\x07'))?;
+                                break
+                            }
+                            if let Some(trigger) = options.triggers.iter()
+                                .find(|t| t.hit(&onion[0], &matrix, pop)) {
+                                message = Some(format!("stopped: {}", trigger));
+                                break
+                            }
                             delta = delta_timer.elapsed();
                             std::thread::sleep(if max_delay > delta {max_delay - delta}
                                                else {min_delay})
                         },
                     } // match end
                 } // loop end
-                stdo.execute(cursor::Show).unwrap();
+                playing = false;
+                stepped!();
+                // paused views like the onion skin come back
+                redraw_all!();
+                stdo.execute(cursor::Show)?;
                 if log {framerate_averages.push(frames/total_timer.elapsed().as_secs() as f64)}
             }
 
             // clear
             Some(KE!('x')) => {
-                match get_event(None) {
-                    Some(KE!('x')) => {
-                        matrix = gen_grid(cols as usize, rows as usize - 1, None);
-                        redraw_all!();
-                    },
-                    _ => (),
+                if confirm!('x', "clear the grid") {
+                    clear!();
+                    edited!();
+                    redraw_all!();
                 }
             }
 
             // change chars
             Some(KE!('c')) => {
-                stdo.execute(cursor::MoveTo(4, rows-1)).unwrap();
-                match get_event(None) {
-                    Some(Event::Key(KeyEvent{code: key, modifiers: _})) => match key {
-                        KeyCode::Char(c) => if valid_chars(c) && c != ch_f {ch_t = c;},
-                        _ => (),
-                    }
-                    _ => (),
+                stdo.execute(cursor::MoveTo(4, rows-1))?;
+                if let Some(Event::Key(KeyEvent{code: KeyCode::Char(c), modifiers: _})) = read_event!(None) {
+                    if valid_chars(c) && c != ch_f {ch_t = c;}
                 }
                 redraw_all!();
-                stdo.execute(cursor::MoveTo(11, rows-1)).unwrap();
-                match get_event(None) {
-                    Some(Event::Key(KeyEvent{code: key, modifiers: _})) => match key {
-                        KeyCode::Char(c) => if valid_chars(c) && c != ch_t {ch_f = c;},
-                        _ => (),
-                    }
-                    _ => (),
+                stdo.execute(cursor::MoveTo(11, rows-1))?;
+                if let Some(Event::Key(KeyEvent{code: KeyCode::Char(c), modifiers: _})) = read_event!(None) {
+                    if valid_chars(c) && c != ch_t {ch_f = c;}
                 }
-                stdo.execute(cursor::MoveTo(cur_col, cur_row)).unwrap();
+                stdo.execute(cursor::MoveTo(cur_col, cur_row))?;
                 redraw_all!();
             }
 
             // mouse click/drag
             Some(Event::Mouse(MouseEvent::Down(button, col, row, _))) => {
-                if row >= rows-1 {continue}
-                let col = col as usize;
-                let row = row as usize;
+                let (col, row) = match grid_pos!(col, row) {
+                    Some(pos) => pos,
+                    None => continue,
+                };
                 match button {
                     MouseButton::Middle => (),
                     _ => {
@@ -470,26 +3015,687 @@ fn main() {
                         if (!matrix[row][col] && button == MouseButton::Left) ||
                            (matrix[row][col] && button == MouseButton::Right) {
                                 grid_toggle(&mut matrix, col, row);
+                                edited!();
+                                if let Some(species) = &mut species {species[row][col] = false}
                                 redraw_all!();
                         }
                         // drag
-                        loop {
-                            match get_event(None) {
-                                Some(Event::Mouse(MouseEvent::Drag(button, col, row, _))) => {
-                                    if row >= rows-1 {break}
-                                    let col = col as usize;
-                                    let row = row as usize;
-                                    if (!matrix[row][col] && button == MouseButton::Left) ||
-                                       (matrix[row][col] && button == MouseButton::Right) {
-                                            grid_toggle(&mut matrix, col, row);
-                                            redraw_all!();
-                                    }
+                        while let Some(Event::Mouse(MouseEvent::Drag(button, col, row, _))) = read_event!(None) {
+                            let (col, row) = match grid_pos!(col, row) {
+                                Some(pos) => pos,
+                                None => break,
+                            };
+                            if (!matrix[row][col] && button == MouseButton::Left) ||
+                               (matrix[row][col] && button == MouseButton::Right) {
+                                    grid_toggle(&mut matrix, col, row);
+                                    edited!();
+                                    if let Some(species) = &mut species {species[row][col] = false}
+                                    redraw_all!();
+                            }
+                        }
+                    }
+                }
+            }
+
+            // show/hide cursor guides
+            Some(KE!('g')) => {
+                guides = !guides;
+                redraw_all!();
+            }
+
+            // a picture of the grid for sharing, saved to a file when there's no way to copy it
+            Some(KE!('I')) => {
+                let png = image::png(&export_view(&matrix, config.export_crop));
+                message = Some(if image::copy_png(&png) {
+                    format!("copied a picture of the grid, {} pixels to a cell", image::CELL_PX)
+                } else {
+                    match std::fs::write(SCREENSHOT_SAVE, &png) {
+                        Ok(()) => format!("no wl-copy or xclip to copy with, saved {} instead", SCREENSHOT_SAVE),
+                        Err(e) => format!("failed to save {}: {}", SCREENSHOT_SAVE, e),
+                    }
+                });
+                redraw_all!();
+            }
+
+            // start a selection at the cursor, finish it, or drop it
+            Some(KE!('v')) => {
+                if selecting.is_some() {
+                    selection = selection!();
+                    selecting = None;
+                } else if selection.is_some() {
+                    selection = None;
+                } else {
+                    selecting = grid_pos!(cur_col, cur_row);
+                    tracing = None;
+                }
+                lasso = None;
+                redraw_all!();
+            }
+
+            // trace a lasso from the cursor, close it back to the start, or drop it
+            Some(KE!('U')) => {
+                if let Some(path) = tracing.take() {
+                    let (grid_cols, grid_rows) = grid_size!();
+                    let mask = lasso_mask(&path, grid_cols, grid_rows);
+                    selection = bounding_box(&mask);
+                    lasso = Some(mask);
+                } else if selection.is_some() {
+                    selection = None;
+                    lasso = None;
+                } else {
+                    tracing = grid_pos!(cur_col, cur_row).map(|pos| vec![pos]);
+                    selecting = None;
+                }
+                redraw_all!();
+            }
+
+            // cycle paint, erase and neither, starting on the cursor's cell
+            Some(KE!('B')) => {
+                brush = match brush {
+                    None => Some(true),
+                    Some(true) => Some(false),
+                    Some(false) => None,
+                };
+                if let (Some(alive), Some((x, y))) = (brush, grid_pos!(cur_col, cur_row)) {
+                    matrix[y][x] = alive;
+                    edited!();
+                }
+                redraw_all!();
+            }
+
+            // fill the dead space around the cursor, or clear the shape under it
+            Some(KE!('F')) => {
+                if let Some((x, y)) = grid_pos!(cur_col, cur_row) {
+                    let alive = matrix[y][x];
+                    let filled = flood_fill(&mut matrix, x, y);
+                    edited!();
+                    message = Some(format!("{} {} cells", if alive {"cleared"} else {"filled"}, filled));
+                }
+                redraw_all!();
+            }
+
+            // flip or turn the whole grid
+            Some(KE!('X')) => {
+                message = Some("transform? h: mirror left to right, v: mirror top to bottom, r: rotate clockwise".to_string());
+                redraw_all!();
+                let how = match event!(None) {
+                    Some(Event::Key(KeyEvent{code: KeyCode::Char(c), ..})) if "hvr".contains(c) => Some(c),
+                    _ => None,
+                };
+                if let Some(how) = how {
+                    // everything else kept cell by cell turns with it
+                    matrix = transform(&matrix, how);
+                    envelope = transform(&envelope, how);
+                    marked = transform(&marked, how);
+                    if let Some(species) = &mut species {*species = transform(species, how);}
+                    if let Some(layer) = &mut layer {layer.cells = transform(&layer.cells, how);}
+                    if let Some(mark) = &mut bookmark {*mark = transform(mark, how);}
+                    let (grid_cols, grid_rows) = grid_size!();
+                    labels = labels.drain(..)
+                        .filter_map(|(x, y, text)| transform_pos(x, y, grid_cols, grid_rows, how).map(|(x, y)| (x, y, text)))
+                        .collect();
+                    // none of these line up with the grid any more
+                    onion.clear();
+                    trail = gen_grid(grid_cols, grid_rows, None);
+                    selection = None;
+                    selecting = None;
+                    lasso = None;
+                    edited!();
+                }
+                redraw_all!();
+            }
+
+            // double the size of the selection, or of everything on the grid about its middle
+            Some(KE!('Z')) => {
+                let (grid_cols, grid_rows) = grid_size!();
+                if let (Some(cells), Some((left, top, right, bottom))) = (selected_cells!(), selection!()) {
+                    for (y, row) in matrix.iter_mut().enumerate().skip(top).take(bottom - top + 1) {
+                        for (x, cell) in row.iter_mut().enumerate().skip(left).take(right - left + 1) {
+                            if in_lasso!(x, y) {*cell = false}
+                        }
+                    }
+                    let cells = upscale(&cells);
+                    let (width, height) = (cells[0].len(), cells.len());
+                    stamp(&mut matrix, &cells, left as isize, top as isize);
+                    selection = Some((left, top, (left + width - 1).min(grid_cols - 1), (top + height - 1).min(grid_rows - 1)));
+                    selecting = None;
+                    lasso = None;
+                    edited!();
+                } else if let Some((left, top, right, bottom)) = bounding_box(&matrix) {
+                    let cells = upscale(&crop(&matrix, (left, top, right, bottom)));
+                    let (col, row) = (left as isize - (right - left + 1) as isize / 2, top as isize - (bottom - top + 1) as isize / 2);
+                    matrix = gen_grid(grid_cols, grid_rows, None);
+                    stamp(&mut matrix, &cells, col, row);
+                    edited!();
+                }
+                redraw_all!();
+            }
+
+            // draw a shape filling the selection
+            Some(KE!('K')) => {
+                match selection!() {
+                    Some((left, top, right, bottom)) => {
+                        message = Some("shape? r: rectangle, R: filled rectangle, e: ellipse, E: filled ellipse".to_string());
+                        redraw_all!();
+                        let (cols, rows) = (right - left + 1, bottom - top + 1);
+                        let cells = match event!(None) {
+                            Some(KE!('r')) => Some(generate::rectangle(cols, rows, false)),
+                            Some(KE!('R')) => Some(generate::rectangle(cols, rows, true)),
+                            Some(KE!('e')) => Some(generate::ellipse(cols, rows, false)),
+                            Some(KE!('E')) => Some(generate::ellipse(cols, rows, true)),
+                            _ => None,
+                        };
+                        if let Some(cells) = cells {
+                            stamp(&mut matrix, &cells, left as isize, top as isize);
+                            selection = None;
+                            selecting = None;
+                            lasso = None;
+                            edited!();
+                        }
+                        message = None;
+                    },
+                    None => message = Some("select where the shape goes with 'v' first".to_string()),
+                }
+                redraw_all!();
+            }
+
+            // start measuring from the cursor, or stop
+            Some(KE!('A')) => {
+                anchor = if anchor.is_some() {None} else {grid_pos!(cur_col, cur_row)};
+                redraw_all!();
+            }
+
+            // pin a label to the cursor's cell, or take it off if nothing's typed
+            Some(KE!('N')) => {
+                if let Some((x, y)) = grid_pos!(cur_col, cur_row) {
+                    let text = prompt!("label: ");
+                    message = None;
+                    if let Some(text) = text {
+                        labels.retain(|(lx, ly, _)| (*lx, *ly) != (x, y));
+                        if !text.trim().is_empty() {labels.push((x, y, text.trim().to_string()))}
+                        edited!();
+                    }
+                }
+                redraw_all!();
+            }
+
+            // LifeHistory
+            Some(KE!('H')) => {
+                show_history = !show_history;
+                redraw_all!();
+            }
+            Some(KE!('M')) => {
+                if let Some((x, y)) = grid_pos!(cur_col, cur_row) {
+                    marked[y][x] = !marked[y][x];
+                    edited!();
+                }
+                redraw_all!();
+            }
+
+            Some(KE!('P')) => {
+                play_cursor = !play_cursor;
+                message = Some(format!("cursor {} during playback", if play_cursor {"shown"} else {"hidden"}));
+                redraw_all!();
+            }
+
+            // show/hide trails
+            Some(KE!('t')) => {
+                trails = !trails;
+                if !trails {
+                    let (grid_cols, grid_rows) = grid_size!();
+                    trail = gen_grid(grid_cols, grid_rows, None);
+                }
+                redraw_all!();
+            }
+
+            // show/hide onion skin
+            Some(KE!('n')) => {
+                onion_skin = !onion_skin;
+                redraw_all!();
+            }
+
+            // show/hide next generation preview
+            Some(KE!('p')) => {
+                preview = !preview;
+                redraw_all!();
+            }
+
+            // flash cells changed by each step
+            Some(KE!('D')) => {
+                diff = !diff;
+                message = Some(format!("diff highlight {}", if diff {"on"} else {"off"}));
+                redraw_all!();
+            }
+
+            // swap which layer is edited, starting an empty one with the same rules the first time
+            Some(KE!('l')) => {
+                let (grid_cols, grid_rows) = grid_size!();
+                let other = layer.take().unwrap_or_else(|| Layer {cells: gen_grid(grid_cols, grid_rows, None), live, birth});
+                layer = Some(Layer {cells: std::mem::replace(&mut matrix, other.cells), live, birth});
+                live = other.live;
+                birth = other.birth;
+                // the history belongs to the other layer now
+                trail = gen_grid(grid_cols, grid_rows, None);
+                envelope = gen_grid(grid_cols, grid_rows, None);
+                onion.clear();
+                if species.is_some() {species = Some(gen_grid(grid_cols, grid_rows, None));}
+                history.clear();
+                undo.reset(&layers!(), &labels, generation);
+                edited!();
+                layer_num = 3 - layer_num;
+                message = Some(format!("editing layer {} of 2", layer_num));
+                redraw_all!();
+            }
+            Some(KE!('L')) => {
+                if layer.take().is_some() {
+                    layer_num = 1;
+                    message = Some("removed the other layer".to_string());
+                }
+                // nothing left for the second pane
+                if dual {
+                    dual = false;
+                    resize!();
+                }
+                redraw_all!();
+            }
+
+            // dual view. halves the grid and copies it into a second pane
+            // which is stepped alongside under its own rules, like a layer.
+            Some(KE!('V')) => {
+                dual = !dual;
+                if dual {
+                    // keep the middle
+                    let (half, _) = grid_size!();
+                    for row in &mut matrix {
+                        let cut = row.len().saturating_sub(half) / 2;
+                        row.drain(..cut);
+                    }
+                    resize!();
+                    layer = Some(Layer {cells: matrix.clone(), live, birth});
+                    layer_num = 1;
+                    let (grid_cols, grid_rows) = grid_size!();
+                    trail = gen_grid(grid_cols, grid_rows, None);
+                    envelope = gen_grid(grid_cols, grid_rows, None);
+                    marked = gen_grid(grid_cols, grid_rows, None);
+                    onion.clear();
+                    edited!();
+                    message = Some("dual view. 'l' swaps panes to edit the right one's rules".to_string());
+                } else {
+                    layer = None;
+                    layer_num = 1;
+                    resize!();
+                }
+                redraw_all!();
+            }
+
+            // two-species mode. everything alive starts out red.
+            Some(KE!('m')) => {
+                let (grid_cols, grid_rows) = grid_size!();
+                species = match species {
+                    Some(_) => None,
+                    None => Some(gen_grid(grid_cols, grid_rows, None)),
+                };
+                redraw_all!();
+            }
+
+            // cosmic rays, a factor of ten at a time
+            Some(KE!('{')) => {
+                cosmic = if cosmic > COSMIC_MIN {cosmic / 10.} else {0.};
+                message = Some(format!("cosmic rays: {:e} per cell per generation", cosmic));
+                redraw_all!();
+            }
+            Some(KE!('}')) => {
+                cosmic = if cosmic > 0. {(cosmic * 10.).min(1.)} else {COSMIC_MIN};
+                message = Some(format!("cosmic rays: {:e} per cell per generation", cosmic));
+                redraw_all!();
+            }
+
+            // cycle edge topologies
+            Some(KE!('E')) => {
+                edges = edges.next();
+                message = Some(format!("edges: {}", edges));
+                redraw_all!();
+            }
+
+            // keep the grid as it is, to compare with later. Ahead of the border, which would take it for 'b'.
+            Some(KE!('b', KeyModifiers::CONTROL)) => {
+                bookmark = Some(matrix.clone());
+                message = Some(format!("bookmarked generation {}, Y shows what's changed since", generation));
+                redraw_all!();
+            }
+
+            // show/hide border
+            Some(KE!('b')) => {
+                border = !border;
+                resize!();
+            }
+
+            // save, either the whole grid or cropped to the live cells
+            Some(KE!('S')) => {
+                message = Some(format!("save to {}? S: whole grid, c: crop to live cells", save_path.display()));
+                redraw_all!();
+                let (grid_cols, grid_rows) = grid_size!();
+                let mut kept = matrix.clone();
+                if life_history!() {
+                    add_history(&mut kept, &envelope);
+                    add_history(&mut kept, &marked);
+                }
+                let bbox = match event!(None) {
+                    Some(KE!('S')) => Some(Some((0, 0, grid_cols - 1, grid_rows - 1))),
+                    Some(KE!('c')) => Some(bounding_box(&kept)),
+                    _ => None,
+                };
+                if let Some(bbox) = bbox {save!(bbox);}
+                redraw_all!();
+            }
+
+            // replace the grid with something generated
+            Some(KE!('G')) => {
+                message = Some("generate? u: soup, n: noise, s: stripes, k: checkers, d: diagonals, o: circle, r: ring".to_string());
+                redraw_all!();
+                let (grid_cols, grid_rows) = grid_size!();
+                let choice = event!(None);
+                let cells = match choice {
+                    Some(KE!('u')) => Some(generate::uniform(&mut rng, grid_cols, grid_rows, 0.5)),
+                    Some(KE!('n')) => Some(generate::noise(&mut rng, grid_cols, grid_rows, NOISE_SCALE)),
+                    Some(KE!('s')) => Some(generate::stripes(grid_cols, grid_rows)),
+                    Some(KE!('k')) => Some(generate::checkerboard(grid_cols, grid_rows)),
+                    Some(KE!('d')) => Some(generate::diagonals(grid_cols, grid_rows, 3)),
+                    Some(KE!('o')) => Some(generate::circle(grid_cols, grid_rows)),
+                    Some(KE!('r')) => Some(generate::ring(grid_cols, grid_rows)),
+                    _ => None,
+                };
+                if let (Some(cells), Some(Event::Key(KeyEvent{code: KeyCode::Char(key), ..}))) = (cells, choice) {
+                    if population(&matrix) == 0 || confirm!(key, "replace the grid") {
+                        clear!();
+                        stamp(&mut matrix, &cells, 0, 0);
+                        edited!();
+                    }
+                }
+                redraw_all!();
+            }
+
+            Some(KE!('i')) => {
+                inverted = !inverted;
+                redraw_all!();
+            }
+
+            // repeat a pattern across the whole grid
+            Some(KE!('T')) => {
+                if let Some(cells) = open_pattern!("tile: ") {
+                    let spacing = prompt!("spacing between copies (1): ")
+                        .map(|s| if s.trim().is_empty() {Ok(1)} else {s.trim().parse::<usize>()});
+                    match spacing {
+                        Some(Ok(spacing)) => {
+                            let (grid_cols, grid_rows) = grid_size!();
+                            let (step_x, step_y) = (cells[0].len() + spacing, cells.len() + spacing);
+                            for row in (0..grid_rows).step_by(step_y) {
+                                for col in (0..grid_cols).step_by(step_x) {
+                                    stamp(&mut matrix, &cells, col as isize, row as isize);
                                 }
-                                _ => break,
                             }
+                            edited!();
+                            message = None;
+                        },
+                        Some(Err(_)) => message = Some("spacing must be a whole number".to_string()),
+                        None => message = None,
+                    }
+                }
+                redraw_all!();
+            }
+
+            // browse for a pattern or session file to open, starting from here
+            Some(KE!('o', KeyModifiers::CONTROL)) => {
+                let mut dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+                let mut all = false;
+                // 0 is the parent directory, then the entries
+                let mut choice = 0;
+                stdo.queue(cursor::Hide)?;
+                let picked = loop {
+                    let entries = match files::list(&dir, all) {
+                        Ok(entries) => entries,
+                        Err(e) => {message = Some(e); break None},
+                    };
+                    let mut text = format!("{}\nws to choose, enter to open, backspace goes up, tab for all files, esc to cancel\n\n",
+                                           dir.display());
+                    let marker = |i: usize| if i == choice {'>'} else {' '};
+                    let mut lines = vec![format!("{} ../", marker(0))];
+                    lines.extend(entries.iter().enumerate()
+                        .map(|(i, entry)| format!("{} {}{}", marker(i + 1), entry.name, if entry.dir {"/"} else {""})));
+                    // scrolled to keep the choice in view
+                    let view = (rows as usize).saturating_sub(4).max(1);
+                    let first = (choice + 1).saturating_sub(view);
+                    text.push_str(&lines[first..].iter().take(view).cloned().collect::<Vec<_>>().join("\n"));
+                    erase!();
+                    redraw(&mut stdo, &text, cols, rows)?;
+                    let up = |dir: &mut PathBuf| if let Some(parent) = dir.parent() {*dir = parent.to_path_buf()};
+                    match event!(None) {
+                        Some(KE!('w')) | Some(Event::Key(KeyEvent{code: KeyCode::Up, ..})) => choice = choice.saturating_sub(1),
+                        Some(KE!('s')) | Some(Event::Key(KeyEvent{code: KeyCode::Down, ..})) => choice = (choice + 1).min(entries.len()),
+                        Some(KE!(' ')) | Some(Event::Key(KeyEvent{code: KeyCode::Enter, ..})) => match choice.checked_sub(1).map(|i| &entries[i]) {
+                            Some(entry) if !entry.dir => break Some(entry.path.clone()),
+                            Some(entry) => {dir = entry.path.clone(); choice = 0},
+                            None => up(&mut dir),
+                        },
+                        Some(Event::Key(KeyEvent{code: KeyCode::Backspace, ..})) => {up(&mut dir); choice = 0},
+                        Some(Event::Key(KeyEvent{code: KeyCode::Tab, ..})) => {all = !all; choice = 0},
+                        Some(Event::Key(KeyEvent{code: KeyCode::Esc, ..})) => break None,
+                        Some(Event::Resize(ncols, nrows)) => {
+                            let (ncols, nrows) = wait_for_size(&mut stdo, ncols, nrows)?;
+                            cols = ncols;
+                            rows = nrows;
+                        },
+                        _ => (),
+                    }
+                };
+                stdo.queue(cursor::Show)?;
+                resize!();
+                if let Some(path) = picked {ghost = load_cells!(&path)}
+                place_ghost!();
+            }
+
+            // open a pattern, and move it around before placing it at the cursor
+            Some(KE!('o')) => {
+                ghost = open_pattern!("open: ");
+                place_ghost!();
+            }
+
+            // open one of the files used lately
+            Some(KE!('Q')) => {
+                let files = recent::load();
+                if files.is_empty() {
+                    message = Some("nothing opened or saved yet".to_string());
+                } else {
+                    let mut text = String::from("recent files. 1-9 to open, anything else to cancel\n\n");
+                    for (i, file) in files.iter().enumerate() {
+                        writeln!(text, "{}  {}", i + 1, file.display()).unwrap();
+                    }
+                    stdo.queue(cursor::Hide)?;
+                    erase!();
+                    redraw(&mut stdo, &text, cols, rows)?;
+                    let choice = match event!(None) {
+                        Some(Event::Key(KeyEvent{code: KeyCode::Char(c), ..})) => c.to_digit(10)
+                            .and_then(|n| files.get((n as usize).checked_sub(1)?)),
+                        _ => None,
+                    };
+                    stdo.queue(cursor::Show)?;
+                    resize!();
+                    if let Some(file) = choice {ghost = load_cells!(file)}
+                }
+                place_ghost!();
+            }
+
+            // show cells added green and removed red since the bookmark. Without one, a grid
+            // saved whole with S or a snapshot file is read in to compare with.
+            Some(KE!('Y')) => {
+                if compare {
+                    compare = false;
+                } else if bookmark.is_some() {
+                    compare = true;
+                } else {
+                    match prompt!("compare with a saved grid (tab completes): ", true) {
+                        Some(path) if !path.trim().is_empty() => match pattern::load(Path::new(path.trim())) {
+                            Ok(pattern) => {
+                                let (grid_cols, grid_rows) = grid_size!();
+                                let mut mark = gen_grid(grid_cols, grid_rows, None);
+                                // where it was on the grid it was saved from
+                                let (col, row) = pattern_origin(&pattern, grid_cols, grid_rows);
+                                stamp(&mut mark, &pattern.cells, col, row);
+                                bookmark = Some(mark);
+                                compare = true;
+                            },
+                            Err(e) => message = Some(Error::Pattern(e).to_string()),
+                        },
+                        _ => message = None,
+                    }
+                }
+                redraw_all!();
+            }
+
+            // how long steps and draws take, in the corner
+            Some(KE!('y')) => {
+                timing = !timing;
+                redraw_all!();
+            }
+
+            // bring back what the last pattern loaded said about itself
+            Some(KE!('j')) => {
+                if about.is_empty() {
+                    message = Some("no name, author or comments to show".to_string());
+                } else {
+                    show_about = true;
+                }
+                redraw_all!();
+            }
+
+            // type or paste in a pattern as text, plaintext or RLE, and stamp it at the cursor.
+            // Terminals send a paste as keys, like it's typed very fast.
+            Some(KE!('W')) => {
+                let mut text = String::new();
+                let done = loop {
+                    // a paste comes in faster than it could be drawn
+                    if !poll(Duration::ZERO)? {
+                        message = Some(format!("paste a pattern then an empty line, esc cancels. {} lines",
+                                               text.lines().count()));
+                        redraw_all!();
+                    }
+                    // raw, a rebound key in the text is still itself
+                    match read_event!(None) {
+                        Some(Event::Key(KeyEvent{code: KeyCode::Enter, ..})) | Some(KE!('j', KeyModifiers::CONTROL)) => {
+                            if text.is_empty() || text.ends_with('\n') {break true}
+                            text.push('\n');
+                        },
+                        Some(Event::Key(KeyEvent{code: KeyCode::Char(c), ..})) => {
+                            text.push(c);
+                            // the end of an RLE body, unless that's a plaintext comment
+                            let line = text.rsplit('\n').next().unwrap_or("");
+                            if c == '!' && !line.starts_with('!') && !line.starts_with('#') {break true}
+                        },
+                        Some(Event::Key(KeyEvent{code: KeyCode::Backspace, ..})) => {text.pop();},
+                        Some(Event::Key(KeyEvent{code: KeyCode::Esc, ..})) => break false,
+                        Some(Event::Resize(c, r)) => {resize!(c, r);},
+                        _ => (),
+                    }
+                };
+                message = None;
+                if done && !text.trim().is_empty() {
+                    match pattern::parse(&text) {
+                        Ok(pattern) if pattern.width() > 0 => {
+                            let (col, row) = ghost_origin!(pattern.cells);
+                            stamp(&mut matrix, &pattern.cells, col, row);
+                            edited!();
+                            message = Some(format!("pasted {}x{}", pattern.width(), pattern.height()));
+                            about = metadata(&pattern);
+                            show_about = true;
+                            last_opened = Some(pattern.cells);
+                        },
+                        Ok(_) => message = Some("pattern is empty".to_string()),
+                        Err(e) => message = Some(Error::Pattern(e).to_string()),
+                    }
+                }
+                redraw_all!();
+            }
+
+            // pick one of the built in patterns to place, by picture
+            Some(KE!('O')) => {
+                let patterns: Vec<Pattern> = library::names().iter().flat_map(|name| library::get(name)).collect();
+                let mut choice = 0;
+                stdo.queue(cursor::Hide)?;
+                let picked = loop {
+                    let mut text = String::from("built in patterns. ws to choose, space/enter to place, esc to cancel\n\n");
+                    // each one's name beside the top of its picture, scrolled to keep the choice in view
+                    let mut lines = Vec::new();
+                    let mut chosen_line = 0;
+                    for (i, (name, pattern)) in library::names().iter().zip(&patterns).enumerate() {
+                        if i == choice {chosen_line = lines.len()}
+                        let marker = if i == choice {'>'} else {' '};
+                        for (n, thumb) in library::thumbnail(&pattern.cells, THUMB_COLS, THUMB_LINES).iter().enumerate() {
+                            let label = if n == 0 {format!("{} {}", marker, name)} else {String::new()};
+                            lines.push(format!("{:<18}{}", label, thumb));
                         }
+                        lines.push(String::new());
+                    }
+                    let view = (rows as usize).saturating_sub(2);
+                    let first = (chosen_line + THUMB_LINES + 1).saturating_sub(view);
+                    text.push_str(&lines.iter().skip(first).take(view).cloned().collect::<Vec<_>>().join("\n"));
+                    erase!();
+                    redraw(&mut stdo, &text, cols, rows)?;
+                    match event!(None) {
+                        Some(KE!('w')) => choice = choice.saturating_sub(1),
+                        Some(KE!('s')) => choice = (choice + 1).min(patterns.len() - 1),
+                        Some(KE!(' ')) | Some(Event::Key(KeyEvent{code: KeyCode::Enter, ..})) => break Some(choice),
+                        Some(Event::Key(KeyEvent{code: KeyCode::Esc, ..})) => break None,
+                        Some(Event::Resize(ncols, nrows)) => {
+                            let (ncols, nrows) = wait_for_size(&mut stdo, ncols, nrows)?;
+                            cols = ncols;
+                            rows = nrows;
+                        },
+                        _ => (),
                     }
+                };
+                stdo.queue(cursor::Show)?;
+                resize!();
+                if let Some(cells) = picked.map(|i| patterns[i].cells.clone()) {
+                    last_opened = Some(cells.clone());
+                    ghost = Some(cells);
+                }
+                place_ghost!();
+            }
+
+            // one line to share the grid with
+            Some(KE!('C')) => {
+                let cells = selected_cells!().unwrap_or_else(|| matrix.clone());
+                let code = pattern::to_code(&Pattern::new(cells, Some(rule_str(live, birth))));
+                copy_to_clipboard(&mut stdo, &code)?;
+                message = Some(format!("copied a {} char seed code. start from it with --code", code.len()));
+                redraw_all!();
+            }
+
+            // look for a grid that steps into this one
+            Some(KE!('R')) => {
+                if birth == 0 {
+                    message = Some("predecessor search doesn't support B0 rules".to_string());
+                } else {
+                    message = Some("searching for a predecessor, esc stops".to_string());
+                    redraw_all!();
+                    // signals stop it too, the next read picks them up
+                    let stop = || match get_event(Some(Duration::ZERO)) {
+                        Ok(event) => matches!(config.remap(event), Some(Event::Key(KeyEvent{code: KeyCode::Esc, ..}))),
+                        Err(_) => true,
+                    };
+                    message = Some(match search::predecessor(&matrix, live, birth, edges, SEARCH_LIMIT, stop) {
+                        Search::Found(pred) => {
+                            matrix = pred;
+                            generation = generation.saturating_sub(1);
+                            edited!();
+                            "predecessor found. 'e' steps back to where you were".to_string()
+                        },
+                        Search::NoneFound => "no predecessor found in region".to_string(),
+                        Search::GaveUp => "predecessor search gave up, try a smaller pattern".to_string(),
+                        Search::Stopped => "predecessor search stopped".to_string(),
+                    });
                 }
+                redraw_all!();
             }
 
             // show/hide help.
@@ -499,11 +3705,27 @@ fn main() {
 
             Some(Event::Resize(c, r)) => {resize!(c, r);},
 
+            Some(KE!('z', KeyModifiers::CONTROL)) => {
+                suspend()?;
+                stdo.queue(cursor::MoveTo(cur_col, cur_row))?;
+                resize!();
+            },
+
             // quit
             // TODO ctrl-c support. use ctrl-c in play and help loops, too.
-            Some(KE!('q')) => match get_event(None) {
-                Some(KE!('q')) => break,
-                _ => (),
+            Some(KE!('q')) => if confirm!('q', "quit") {
+                // a restored session keeps everything anyway
+                if config.restore_session || !dirty {break}
+                message = Some(format!("unsaved changes. s: save to {} and quit, d: discard, anything else cancels",
+                                       save_path.display()));
+                redraw_all!();
+                let (grid_cols, grid_rows) = grid_size!();
+                match event!(None) {
+                    Some(KE!('s')) => if save!(Some((0, 0, grid_cols - 1, grid_rows - 1))) {break},
+                    Some(KE!('d')) => break,
+                    _ => (),
+                }
+                redraw_all!();
             },
 
             _ => (),
@@ -511,13 +3733,36 @@ fn main() {
     } // loop end
 
     // cleanup
-    queue!(
-        stdo,
-        terminal::LeaveAlternateScreen,
-        event::DisableMouseCapture,
-        ).unwrap();
-    stdo.flush().unwrap();
-    terminal::disable_raw_mode().unwrap();
+    stdo.flush()?;
+    restore_terminal();
+
+    if config.restore_session {
+        // the whole grid, placed back where it was
+        let (grid_cols, grid_rows) = grid_size!();
+        let mut pattern = Pattern::new(matrix.clone(), Some(rule_str(live, birth)));
+        pattern.pos = Some((-((grid_cols / 2) as i64), -((grid_rows / 2) as i64)));
+        pattern.generation = Some(generation);
+        pattern.labels = labels.iter().map(|(x, y, text)| (*x as i64, *y as i64, text.clone())).collect();
+        let settings = [
+            ("edges", edges.to_string()),
+            ("framerate", config.framerates[framerate].to_string()),
+            ("chars", format!("{} {}", ch_t as u32, ch_f as u32)),
+        ];
+        if let Err(e) = session::save(&pattern, &settings) {
+            eprintln!("failed to save the session {}", e);
+        }
+    }
+
+    let final_pop = population(&matrix);
+    println!("Simulated {} generations of {} in {:.1}s", simulated, rule_str(live, birth), session.elapsed().as_secs_f64());
+    println!("Population: {} at peak, {} at the end\n", peak.max(final_pop), final_pop);
+
+    if let (Some((gif, _)), Some(path)) = (recording, &options.gif) {
+        match gif.finish() {
+            Ok(frames) => println!("Recorded {} frames to {}\n", frames, path.display()),
+            Err(e) => eprintln!("failed to write {}: {}", path.display(), e),
+        }
+    }
 
     if log {
         if step_times.len() > 1 {
@@ -530,4 +3775,50 @@ fn main() {
         }
         println!("Playback average framerates:\n{:?}", framerate_averages);
     }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn b0_grids_stay_real() {
+        // B0/S0, every cell with no neighbors comes alive and nothing else
+        let empty = gen_grid(5, 4, None);
+        let lit = rule_step(&empty, 0, 0, Edges::Dead, 0);
+        assert_eq!(population(&lit), 20);
+        // and all of space goes out again, right up to the edges
+        assert_eq!(population(&rule_step(&lit, 0, 0, Edges::Dead, 1)), 0);
+    }
+
+    #[test]
+    fn render_frame_golden() {
+        let grid = vec![vec![true, false, false, true], vec![false, true, true, false]];
+        assert_eq!(render_frame(&grid, '#', '.', false, "tb", 4, 3), "#..#\n.##.\ntb\n");
+        assert_eq!(render_frame(&grid, '#', '.', true, "tb", 6, 5), "+----+\n|#..#|\n|.##.|\n+----+\ntb\n");
+        // blank lines under a grid smaller than the screen
+        assert_eq!(render_frame(&grid, '#', '.', false, "tb", 8, 5), "#..#\n.##.\n\n\ntb\n");
+        // and a bigger one cut down to fit, toolbar too
+        assert_eq!(render_frame(&grid, '#', '.', true, "toolbar", 3, 3), "+--\n|#.\ntoo\n");
+    }
+
+    #[test]
+    fn stepper_matches_rule_step() {
+        for edges in Edges::ALL {
+            let mut grid = generate::uniform(&mut Rng::new(7), 30, 20, 0.3);
+            let mut stepper = Stepper::new();
+            for generation in 0..60 {
+                let expected = rule_step(&grid, 2, 3, edges, generation);
+                grid = stepper.step(&grid, 2, 3, edges, generation);
+                assert!(grid == expected, "{} edges, generation {}", edges, generation);
+                // an edit between steps, as the cursor would make
+                if generation % 7 == 3 {
+                    grid[5][generation as usize % 30] ^= true;
+                    stepper.touch(generation as usize % 30, 5);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,433 @@
+// Reading pattern files. Supports RLE and plaintext (.cells) formats.
+
+use std::path::Path;
+
+
+// a loaded pattern, trimmed to its own width/height.
+pub struct Pattern {
+    // rows of cells, all the same length
+    pub cells: Vec<Vec<bool>>,
+    // the rule from an RLE header, as written
+    pub rule: Option<String>,
+    // comment lines as found in the file, eg "#N Glider" or "!Name: Glider"
+    pub comments: Vec<String>,
+    // from a #CXRLE line. Where the top-left cell sits, relative to the middle of the grid.
+    pub pos: Option<(i64, i64)>,
+    // also from #CXRLE, the generation it was saved at
+    pub generation: Option<u64>,
+    // for LifeHistory patterns, cells that have ever been alive and cells that are marked.
+    // Both the same size as cells.
+    pub history: Option<Vec<Vec<bool>>>,
+    pub marked: Option<Vec<Vec<bool>>>,
+    // text pinned to cells, from the top-left of the pattern
+    pub labels: Vec<(i64, i64, String)>,
+}
+
+impl Pattern {
+    pub fn new(cells: Vec<Vec<bool>>, rule: Option<String>) -> Self {
+        Pattern {cells, rule, comments: Vec::new(), pos: None, generation: None, history: None, marked: None, labels: Vec::new()}
+    }
+
+    // the text of the first comment with one of the given prefixes
+    fn comment(&self, prefixes: &[&str]) -> Option<&str> {
+        self.comments.iter().find_map(|c| prefixes.iter().find_map(|p| c.strip_prefix(p)).map(str::trim))
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.comment(&["#N", "!Name:"])
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.comment(&["#O", "!Author:"])
+    }
+
+    // free-form comments, leaving out names, authors and anything machine-readable
+    pub fn notes(&self) -> Vec<&str> {
+        self.comments.iter().filter_map(|c| {
+            if let Some(note) = c.strip_prefix("#C").or_else(|| c.strip_prefix("#c")) {
+                if !note.starts_with("XRLE") {return Some(note.trim())}
+            } else if let Some(note) = c.strip_prefix('!') {
+                if !note.starts_with("Name:") && !note.starts_with("Author:") {return Some(note.trim())}
+            }
+            None
+        }).collect()
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.iter().map(|row| row.iter().filter(|c| **c).count()).sum()
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.first().map_or(0, |row| row.len())
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+
+// reads a pattern file, picking the format from the extension or failing that the contents
+pub fn load(path: &Path) -> Result<Pattern, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let result = match path.extension().and_then(|e| e.to_str()) {
+        Some("rle") => parse_rle(&text),
+        Some("cells") => parse_plaintext(&text),
+        _ => parse(&text),
+    };
+    result.map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+
+fn has_header(text: &str) -> bool {
+    text.lines().any(|l| l.trim_start().starts_with("x ") || l.trim_start().starts_with("x="))
+}
+
+
+// text in either format, telling them apart by the RLE header. Headerless RLE,
+// like a bare 'bo$2bo$3o!' copied off a page, is spotted by its closing '!'.
+pub fn parse(text: &str) -> Result<Pattern, String> {
+    let mut body = text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('!') && !l.starts_with('#'));
+    if has_header(text) {
+        parse_rle(text)
+    } else if body.next_back().is_some_and(|l| l.ends_with('!')) {
+        parse_rle(&format!("x = 0, y = 0\n{}", text))
+    } else {
+        parse_plaintext(text)
+    }
+}
+
+
+// pads every row out to the widest one, and to at least min_width/min_height
+fn square_up<T: Clone + Default>(mut cells: Vec<Vec<T>>, min_width: usize, min_height: usize) -> Vec<Vec<T>> {
+    let width = cells.iter().map(|row| row.len()).max().unwrap_or(0).max(min_width);
+    if cells.len() < min_height {
+        cells.resize(min_height, Vec::new());
+    }
+    for row in &mut cells {
+        row.resize(width, T::default());
+    }
+    cells
+}
+
+
+// plaintext: '!' comment lines, '.' dead and 'O' alive.
+pub fn parse_plaintext(text: &str) -> Result<Pattern, String> {
+    let mut cells = Vec::new();
+    let mut comments = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        if line.starts_with('!') {
+            comments.push(line.to_string());
+            continue
+        }
+        let row = line.trim_end().chars().map(|c| match c {
+            '.' => Ok(false),
+            'O' | '*' => Ok(true),
+            _ => Err(format!("line {}: unexpected '{}'", n + 1, c)),
+        }).collect::<Result<Vec<bool>, String>>()?;
+        cells.push(row);
+    }
+    let mut pattern = Pattern::new(square_up(cells, 0, 0), None);
+    pattern.comments = comments;
+    Ok(pattern)
+}
+
+
+// Golly's extended header, eg "#CXRLE Pos=-12,-5 Gen=300". Unknown fields are skipped.
+fn parse_cxrle(line: &str, pattern: &mut Pattern) -> Result<(), String> {
+    for field in line.split_whitespace().skip(1) {
+        let bad = || format!("bad #CXRLE field '{}'", field);
+        if let Some(xy) = field.strip_prefix("Pos=") {
+            let (x, y) = xy.split_once(',').ok_or_else(bad)?;
+            pattern.pos = Some((x.parse().map_err(|_| bad())?, y.parse().map_err(|_| bad())?));
+        } else if let Some(g) = field.strip_prefix("Gen=") {
+            pattern.generation = Some(g.parse().map_err(|_| bad())?);
+        }
+    }
+    Ok(())
+}
+
+
+// labels ride along as comments other programs will just show, eg "#C label 4,-2 eater here"
+const LABEL_PREFIX: &str = "#C label ";
+
+fn parse_label(line: &str) -> Option<(i64, i64, String)> {
+    let (pos, text) = line.strip_prefix(LABEL_PREFIX)?.split_once(' ')?;
+    let (x, y) = pos.split_once(',')?;
+    Some((x.parse().ok()?, y.parse().ok()?, text.to_string()))
+}
+
+
+// the biggest pattern an RLE file can make, on a side and in all. A header or run count
+// can ask for any size in a few bytes, so they're checked before anything's allocated.
+const MAX_SIDE: usize = 1 << 15;
+const MAX_CELLS: usize = 1 << 26;
+
+// Golly's LifeHistory states, written '.' then 'A' to 'F'. Conway's rules, with
+// extra off states to remember things by.
+const OFF: u8 = 0;
+const ON: u8 = 1;
+// off, but has been on
+const HISTORY: u8 = 2;
+const MARKED_ON: u8 = 3;
+const MARKED_OFF: u8 = 4;
+// on at the start. read as plain on
+const START_ON: u8 = 5;
+// 6 is a boundary, read as off
+
+fn is_life_history(rule: &Option<String>) -> bool {
+    rule.as_deref().is_some_and(|r| r.eq_ignore_ascii_case("LifeHistory"))
+}
+
+
+// RLE: '#' comment lines, an 'x = m, y = n, rule = ...' header, then runs of
+// 'b' dead, 'o' alive and '$' end of row, finished by '!'.
+// Any other letter is some other live state, which we treat as plain alive,
+// except for LifeHistory where the history and marks are kept too.
+pub fn parse_rle(text: &str) -> Result<Pattern, String> {
+    let (labels, comments): (Vec<&str>, Vec<&str>) = text.lines().filter(|l| l.starts_with('#'))
+        .partition(|l| parse_label(l).is_some());
+    let mut lines = text.lines().filter(|l| !l.starts_with('#'));
+    let header = lines.by_ref().find(|l| !l.trim().is_empty()).ok_or("missing header")?;
+
+    let (mut width, mut height) = (0, 0);
+    let mut rule = None;
+    for field in header.split(',') {
+        let mut kv = field.splitn(2, '=');
+        let key = kv.next().unwrap_or("").trim();
+        let value = kv.next().map(|v| v.trim());
+        match (key, value) {
+            ("x", Some(v)) => width = v.parse().ok().filter(|w| *w <= MAX_SIDE).ok_or_else(|| format!("bad width '{}'", v))?,
+            ("y", Some(v)) => height = v.parse().ok().filter(|h| *h <= MAX_SIDE).ok_or_else(|| format!("bad height '{}'", v))?,
+            ("rule", Some(r)) => rule = Some(r.to_string()),
+            _ => return Err(format!("bad header field '{}'", field.trim())),
+        }
+    }
+
+    if width * height > MAX_CELLS {return Err(format!("{}x{} is too big", width, height))}
+
+    let life_history = is_life_history(&rule);
+    let mut states: Vec<Vec<u8>> = vec![Vec::new()];
+    // the widest row so far, to keep the whole thing under MAX_CELLS
+    let mut widest = width;
+    let mut count = String::new();
+    'body: for line in lines {
+        for c in line.chars() {
+            if c.is_whitespace() {continue}
+            if c.is_ascii_digit() {
+                count.push(c);
+                continue
+            }
+            let run: usize = if count.is_empty() {1} else {
+                count.parse().ok().filter(|n| *n <= MAX_SIDE).ok_or_else(|| format!("bad run length '{}'", count))?
+            };
+            count.clear();
+            let state = match c {
+                '!' => break 'body,
+                '$' => {
+                    if states.len() + run > MAX_SIDE || (states.len() + run).max(height) * widest > MAX_CELLS {
+                        return Err("too many rows".to_string())
+                    }
+                    for _ in 0..run {states.push(Vec::new())}
+                    continue
+                },
+                'b' | '.' => OFF,
+                'A'..='F' if life_history => c as u8 - b'A' + 1,
+                c if c.is_ascii_alphabetic() => ON,
+                _ => return Err(format!("unexpected '{}'", c)),
+            };
+            widest = widest.max(states.last().unwrap().len() + run);
+            if widest > MAX_SIDE || states.len().max(height) * widest > MAX_CELLS {
+                return Err("row too long".to_string())
+            }
+            states.last_mut().unwrap().extend(std::iter::repeat_n(state, run));
+        }
+    }
+
+    let states = square_up(states, width, height);
+    let cells_where = |f: fn(u8) -> bool| states.iter().map(|row| row.iter().map(|s| f(*s)).collect()).collect();
+    let mut pattern = Pattern::new(cells_where(|s| matches!(s, ON | MARKED_ON | START_ON)), rule);
+    pattern.comments = comments.into_iter().map(String::from).collect();
+    pattern.labels = labels.into_iter().filter_map(parse_label).collect();
+    if life_history {
+        pattern.history = Some(cells_where(|s| matches!(s, ON | HISTORY | MARKED_ON | START_ON)));
+        pattern.marked = Some(cells_where(|s| matches!(s, MARKED_ON | MARKED_OFF)));
+    }
+    if let Some(line) = text.lines().find(|l| l.starts_with("#CXRLE")) {
+        parse_cxrle(line, &mut pattern)?;
+    }
+    Ok(pattern)
+}
+
+
+//// Writing ////
+
+// writes a pattern file, in plaintext for .cells and RLE otherwise
+pub fn save(path: &Path, pattern: &Pattern) -> Result<(), String> {
+    let text = match path.extension().and_then(|e| e.to_str()) {
+        Some("cells") => to_plaintext(pattern),
+        _ => to_rle(pattern),
+    };
+    std::fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+
+// what a comment line says, and what it's for. Lets '#N' and '!Name:' turn into each other.
+enum Comment<'a> {
+    Name(&'a str),
+    Author(&'a str),
+    Note(&'a str),
+}
+
+// machine-readable lines are left out, they're written fresh from the pattern
+fn kept_comments(pattern: &Pattern) -> impl Iterator<Item = Comment<'_>> {
+    pattern.comments.iter().filter_map(|c| {
+        if c.starts_with("#CXRLE") || parse_label(c).is_some() {return None}
+        if let Some(name) = c.strip_prefix("#N").or_else(|| c.strip_prefix("!Name:")) {
+            Some(Comment::Name(name.trim()))
+        } else if let Some(author) = c.strip_prefix("#O").or_else(|| c.strip_prefix("!Author:")) {
+            Some(Comment::Author(author.trim()))
+        } else {
+            c.strip_prefix("#C").or_else(|| c.strip_prefix("#c")).or_else(|| c.strip_prefix('!'))
+                .map(|note| Comment::Note(note.trim()))
+        }
+    })
+}
+
+
+pub fn to_plaintext(pattern: &Pattern) -> String {
+    let mut result = String::new();
+    for comment in kept_comments(pattern) {
+        match comment {
+            Comment::Name(name) => result += &format!("!Name: {}\n", name),
+            Comment::Author(author) => result += &format!("!Author: {}\n", author),
+            Comment::Note(note) => result += &format!("!{}\n", note),
+        }
+    }
+    for row in &pattern.cells {
+        result.extend(row.iter().map(|c| if *c {'O'} else {'.'}));
+        result.push('\n');
+    }
+    result
+}
+
+
+pub fn to_rle(pattern: &Pattern) -> String {
+    fn push(runs: &mut Vec<(usize, char)>, tag: char) {
+        match runs.last_mut() {
+            Some((count, last)) if *last == tag => *count += 1,
+            _ => runs.push((1, tag)),
+        }
+    }
+
+    // LifeHistory states when there's history or marks to keep, otherwise plain on and off
+    let life_history = pattern.history.is_some() || pattern.marked.is_some();
+    let get = |grid: &Option<Vec<Vec<bool>>>, x: usize, y: usize| grid.as_ref().is_some_and(|g| g[y][x]);
+    let state = |x: usize, y: usize| match (pattern.cells[y][x], get(&pattern.marked, x, y)) {
+        (true, true) => MARKED_ON,
+        (true, false) => ON,
+        (false, true) => MARKED_OFF,
+        (false, false) if get(&pattern.history, x, y) => HISTORY,
+        _ => OFF,
+    };
+
+    // (count, tag) runs, merging rows and dropping dead cells at the end of each
+    let mut runs: Vec<(usize, char)> = Vec::new();
+    for y in 0..pattern.height() {
+        if y > 0 {push(&mut runs, '$')}
+        let len = (0..pattern.width()).rposition(|x| state(x, y) != OFF).map_or(0, |i| i + 1);
+        for x in 0..len {
+            push(&mut runs, match state(x, y) {
+                OFF if life_history => '.',
+                OFF => 'b',
+                s if life_history => (b'A' + s - 1) as char,
+                _ => 'o',
+            });
+        }
+    }
+    // trailing empty rows are implied by the header
+    while let Some((_, '$')) = runs.last() {runs.pop();}
+
+    let mut result = String::new();
+    for comment in kept_comments(pattern) {
+        match comment {
+            Comment::Name(name) => result += &format!("#N {}\n", name),
+            Comment::Author(author) => result += &format!("#O {}\n", author),
+            Comment::Note(note) => result += &format!("{}\n", format!("#C {}", note).trim_end()),
+        }
+    }
+    if pattern.pos.is_some() || pattern.generation.is_some() {
+        result += "#CXRLE";
+        if let Some((x, y)) = pattern.pos {result += &format!(" Pos={},{}", x, y)}
+        if let Some(generation) = pattern.generation {result += &format!(" Gen={}", generation)}
+        result.push('\n');
+    }
+    for (x, y, text) in &pattern.labels {
+        result += &format!("{}{},{} {}\n", LABEL_PREFIX, x, y, text);
+    }
+    result += &format!("x = {}, y = {}", pattern.width(), pattern.height());
+    if life_history {
+        result += ", rule = LifeHistory";
+    } else if let Some(rule) = &pattern.rule {
+        result += &format!(", rule = {}", rule);
+    }
+    result.push('\n');
+
+    // lines are kept under 70 chars, as is customary
+    let mut line = String::new();
+    for (count, tag) in runs {
+        let run = if count > 1 {format!("{}{}", count, tag)} else {tag.to_string()};
+        if line.len() + run.len() > 70 {
+            result += &line;
+            result.push('\n');
+            line.clear();
+        }
+        line += &run;
+    }
+    result + &line + "!\n"
+}
+
+
+//// Seed codes ////
+
+// Patterns as one line of text to paste into a chat, eg "cl1:B3/S23:3x3:bo$2bo$3o!".
+// The size is the whole grid, so dead space around the pattern comes along too.
+const CODE_PREFIX: &str = "cl1";
+
+pub fn to_code(pattern: &Pattern) -> String {
+    let rle = to_rle(&Pattern::new(pattern.cells.clone(), None));
+    let body: String = rle.lines().skip(1).collect();
+    format!("{}:{}:{}x{}:{}", CODE_PREFIX, pattern.rule.as_deref().unwrap_or(""),
+            pattern.width(), pattern.height(), body)
+}
+
+
+pub fn from_code(code: &str) -> Result<Pattern, String> {
+    let mut parts = code.trim().splitn(4, ':');
+    if parts.next() != Some(CODE_PREFIX) {return Err("not a cursed_life seed code".to_string())}
+    let (rule, size, body) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(rule), Some(size), Some(body)) => (rule, size, body),
+        _ => return Err("seed code is cut short".to_string()),
+    };
+    let (width, height) = size.split_once('x').ok_or_else(|| format!("bad size '{}'", size))?;
+    let mut header = format!("x = {}, y = {}", width, height);
+    if !rule.is_empty() {header += &format!(", rule = {}", rule)}
+    parse_rle(&format!("{}\n{}", header, body)).map_err(|e| format!("bad seed code: {}", e))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rle_sizes_are_capped() {
+        assert!(parse_rle("x = 3, y = 3\nbo$2bo$3o!").is_ok());
+        assert!(parse_rle("x = 4000000000, y = 1\no!").is_err());
+        assert!(parse_rle("x = 30000, y = 30000\no!").is_err());
+        assert!(parse_rle("x = 0, y = 0\n999999999o!").is_err());
+        assert!(parse_rle("x = 0, y = 0\n999999999$o!").is_err());
+        // each fine alone, too big together
+        assert!(parse_rle("x = 0, y = 0\n30000o$30000$o!").is_err());
+    }
+}
@@ -0,0 +1,76 @@
+// Threads kept for the whole run to split drawing and stepping between, so a big grid
+// doesn't start and join new ones every frame. One job at a time, handed out a band at
+// a time to whichever thread's free, the caller's included.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex, MutexGuard, Once};
+use std::thread;
+
+// the work for one band, with its lifetime forgotten. run() waits for every band
+// to finish before returning, so what it borrows outlives every call.
+type Work = &'static (dyn Fn(usize) + Sync);
+
+struct State {
+    work: Option<Work>,
+    bands: usize,
+    // the next band to hand out, and how many are being worked on
+    next: usize,
+    running: usize,
+    panicked: bool,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {work: None, bands: 0, next: 0, running: 0, panicked: false});
+// workers wait on it for work, run() for them to finish it
+static CHANGED: Condvar = Condvar::new();
+// held through a run, for callers on more than one thread
+static JOB: Mutex<()> = Mutex::new(());
+static START: Once = Once::new();
+
+// every core, the caller's included
+pub fn threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+// does the band taken off state, handing the lock back after
+fn take_band(mut state: MutexGuard<State>) -> MutexGuard<State> {
+    let (work, band) = (state.work.expect("only taken while there's work"), state.next);
+    state.next += 1;
+    state.running += 1;
+    drop(state);
+    let ok = panic::catch_unwind(AssertUnwindSafe(|| work(band))).is_ok();
+    let mut state = STATE.lock().unwrap();
+    state.running -= 1;
+    state.panicked |= !ok;
+    state
+}
+
+fn worker() {
+    let mut state = STATE.lock().unwrap();
+    loop {
+        if state.work.is_some() && state.next < state.bands {
+            state = take_band(state);
+            CHANGED.notify_all();
+        } else {
+            state = CHANGED.wait(state).unwrap();
+        }
+    }
+}
+
+// calls work(band) for every band in 0..bands, across the pool, and returns once
+// they're all done. A panic in any of them comes back out here.
+pub fn run<F: Fn(usize) + Sync>(bands: usize, work: F) {
+    START.call_once(|| for _ in 1..threads() {thread::spawn(worker);});
+    let _job = JOB.lock().unwrap_or_else(|e| e.into_inner());
+    let work: &(dyn Fn(usize) + Sync) = &work;
+    // only kept until every band's finished below
+    let work = unsafe {std::mem::transmute::<&(dyn Fn(usize) + Sync), Work>(work)};
+    let mut state = STATE.lock().unwrap();
+    *state = State {work: Some(work), bands, next: 0, running: 0, panicked: false};
+    CHANGED.notify_all();
+    while state.next < bands {state = take_band(state)}
+    while state.running > 0 {state = CHANGED.wait(state).unwrap()}
+    state.work = None;
+    let panicked = state.panicked;
+    drop(state);
+    if panicked {panic!("a band of work panicked")}
+}
@@ -0,0 +1,40 @@
+// Pattern files opened or saved lately, newest first, for quick opening with 'Q'.
+// Kept one path to a line in 'recent' beside the config file.
+
+use std::path::{Path, PathBuf};
+
+use crate::config;
+
+
+// how many are remembered
+pub const MAX: usize = 9;
+
+
+fn path() -> Option<PathBuf> {
+    Some(config::path()?.with_file_name("recent"))
+}
+
+
+// missing or unreadable just means nothing's been opened yet
+pub fn load() -> Vec<PathBuf> {
+    path().and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|text| text.lines().filter(|l| !l.is_empty()).map(PathBuf::from).take(MAX).collect())
+        .unwrap_or_default()
+}
+
+
+// puts a file at the top of the list. Paths are made absolute so they still work
+// from another directory.
+pub fn add(file: &Path) -> Result<(), String> {
+    let store = path().ok_or_else(|| "nowhere to keep recent files".to_string())?;
+    let file = std::fs::canonicalize(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+    let mut files = load();
+    files.retain(|f| *f != file);
+    files.insert(0, file);
+    files.truncate(MAX);
+    if let Some(dir) = store.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let text: String = files.iter().map(|f| format!("{}\n", f.display())).collect();
+    std::fs::write(&store, text).map_err(|e| format!("{}: {}", store.display(), e))
+}
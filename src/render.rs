@@ -0,0 +1,119 @@
+// Prints a frame of a pattern as it'd be drawn on a terminal of a given size,
+// without opening one. Nothing's read from the config, so the same pattern and flags
+// always print the same text, for comparing against a saved copy.
+
+use std::path::PathBuf;
+
+use super::{gen_grid, gen_toolbar, nearest_framerate, parse_rule, pattern_origin, render_frame, rule_step, stamp, valid_chars, Edges};
+use crate::config::Config;
+use crate::pattern::{self, Pattern};
+
+
+pub struct RenderOptions {
+    path: PathBuf,
+    // the terminal's, toolbar and border included
+    size: (usize, usize),
+    gens: u64,
+    // from the pattern if not given
+    rule: Option<(i32, i32)>,
+    edges: Edges,
+    border: bool,
+    // the config's defaults if not given
+    alive: Option<char>,
+    dead: Option<char>,
+}
+
+// everything after 'render' on the command line
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<RenderOptions, String> {
+    let mut path = None;
+    let mut options = RenderOptions {
+        path: PathBuf::new(), size: (80, 24), gens: 0, rule: None, edges: Edges::Dead, border: false, alive: None, dead: None,
+    };
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            path = Some(PathBuf::from(arg));
+            continue
+        }
+        if arg == "--border" {
+            options.border = true;
+            continue
+        }
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        let bad = || format!("bad value '{}' for {}", value, arg);
+        let char_value = || {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if valid_chars(c) => Ok(c),
+                _ => Err(bad()),
+            }
+        };
+        match arg.as_str() {
+            "--size" => {
+                let (cols, rows) = value.split_once('x').ok_or_else(bad)?;
+                let size = (cols.parse().map_err(|_| bad())?, rows.parse().map_err(|_| bad())?);
+                // room for a cell and the toolbar
+                if size.0 == 0 || size.1 < 2 {return Err(bad())}
+                options.size = size;
+            },
+            "--gens" => options.gens = value.parse().map_err(|_| bad())?,
+            "--rule" => options.rule = Some(parse_rule(&value).ok_or_else(bad)?),
+            "--edges" => options.edges = Edges::parse(&value).ok_or_else(bad)?,
+            "--alive" => options.alive = Some(char_value()?),
+            "--dead" => options.dead = Some(char_value()?),
+            _ => return Err(format!("unknown render flag {}", arg)),
+        }
+    }
+    options.path = path.ok_or_else(|| "render needs a pattern file".to_string())?;
+    Ok(options)
+}
+
+
+pub fn run(options: RenderOptions) -> Result<(), String> {
+    let pattern = pattern::load(&options.path)?;
+    print!("{}", frame(&options, &pattern)?);
+    Ok(())
+}
+
+
+fn frame(options: &RenderOptions, pattern: &Pattern) -> Result<String, String> {
+    let (live, birth) = match (options.rule, &pattern.rule) {
+        (Some(rule), _) => rule,
+        (None, Some(rule)) => parse_rule(rule).ok_or_else(|| format!("rule {} not supported", rule))?,
+        (None, None) => (2, 3),
+    };
+    let config = Config::default();
+    let (ch_t, ch_f) = (options.alive.unwrap_or(config.ch_t), options.dead.unwrap_or(config.ch_f));
+
+    // sized as the UI would, and placed where it'd put a loaded pattern
+    let (cols, rows) = options.size;
+    let off = if options.border && cols > 2 && rows > 3 {1} else {0};
+    let (grid_cols, grid_rows) = (cols - off * 2, rows - 1 - off * 2);
+    let mut grid = gen_grid(grid_cols, grid_rows, None);
+    let (col, row) = pattern_origin(pattern, grid_cols, grid_rows);
+    stamp(&mut grid, &pattern.cells, col, row);
+    for generation in 0..options.gens {
+        grid = rule_step(&grid, live, birth, options.edges, generation);
+    }
+
+    let framerate = config.framerates[nearest_framerate(&config.framerates, 15.)];
+    let toolbar = gen_toolbar(ch_t, ch_f, live, birth, framerate, (0, 0));
+    Ok(render_frame(&grid, ch_t, ch_f, off > 0, &toolbar, cols, rows))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pos_places_as_the_ui_does() {
+        let options = parse_args(["x.rle", "--size", "8x4", "--alive", "#", "--dead", "."].iter().map(|a| a.to_string())).unwrap();
+        let pattern = pattern::parse_rle("#CXRLE Pos=-3,-1\nx = 2, y = 1\n2o!").unwrap();
+        let text = frame(&options, &pattern).unwrap();
+        assert_eq!(text.lines().take(3).collect::<Vec<_>>(), [".##.....", "........", "........"]);
+        // without a Pos it's centered
+        let pattern = pattern::parse_rle("x = 2, y = 1\n2o!").unwrap();
+        let text = frame(&options, &pattern).unwrap();
+        assert_eq!(text.lines().take(3).collect::<Vec<_>>(), ["........", "...##...", "........"]);
+    }
+}
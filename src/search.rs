@@ -0,0 +1,151 @@
+// Reverse search for a grid that steps into the current one.
+
+use super::{bounding_box, neighbors, next_state, Edges};
+
+
+pub enum Search {
+    Found(Vec<Vec<bool>>),
+    // every candidate in the region was ruled out
+    NoneFound,
+    // hit the limit before finishing
+    GaveUp,
+    // stop() said to
+    Stopped,
+}
+
+
+// tries between asking whether to stop
+const STOP_POLL: u64 = 1 << 16;
+
+
+struct Searcher<'a> {
+    target: &'a [Vec<bool>],
+    live: i32,
+    birth: i32,
+    edges: Edges,
+    // cells of the predecessor being decided, in order
+    cells: Vec<(usize, usize)>,
+    // checks[i]: target cells whose whole neighborhood is decided once cells[i] is set
+    checks: Vec<Vec<(usize, usize)>>,
+    pred: Vec<Vec<bool>>,
+    tries: u64,
+    limit: u64,
+}
+
+impl Searcher<'_> {
+    // whether the predecessor steps into the target at x, y
+    fn consistent(&self, x: usize, y: usize) -> bool {
+        let (cols, rows) = (self.pred[0].len(), self.pred.len());
+        let neighbors = neighbors(x, y, cols, rows, self.edges).filter(|&(nx, ny)| self.pred[ny][nx]).count();
+        next_state(self.pred[y][x], neighbors as i32, self.live, self.birth) == self.target[y][x]
+    }
+
+    // Decides cells in order, backing up when a check fails. A loop rather than recursing
+    // a level per cell, which big regions would overflow the stack with.
+    fn search(&mut self, stop: &mut dyn FnMut() -> bool) -> Search {
+        // values[i]: how many of false, true cells[i] has had
+        let mut values = vec![0u8; self.cells.len()];
+        let mut i = 0;
+        loop {
+            if i == self.cells.len() {return Search::Found(self.pred.clone())}
+            let (x, y) = self.cells[i];
+            if values[i] == 2 {
+                values[i] = 0;
+                self.pred[y][x] = false;
+                if i == 0 {return Search::NoneFound}
+                i -= 1;
+                continue
+            }
+            self.pred[y][x] = values[i] == 1;
+            values[i] += 1;
+            self.tries += 1;
+            if self.tries > self.limit {return Search::GaveUp}
+            if self.tries.is_multiple_of(STOP_POLL) && stop() {return Search::Stopped}
+            if self.checks[i].iter().all(|&(cx, cy)| self.consistent(cx, cy)) {i += 1}
+        }
+    }
+}
+
+
+// Live cells of the predecessor are kept within the target's bounding box plus one,
+// since anything further out couldn't reach it. With wrapping edges that misses
+// predecessors spread over the seam, but whatever's found still steps into the target.
+// limit caps the number of cell values tried, and stop is asked every so often whether to give up early.
+// B0 rules aren't handled, as the dead space outside would be born.
+pub fn predecessor(target: &[Vec<bool>], live: i32, birth: i32, edges: Edges, limit: u64,
+                   mut stop: impl FnMut() -> bool) -> Search {
+    let (rows, cols) = (target.len(), target.first().map_or(0, |r| r.len()));
+    let (left, top, right, bottom) = match bounding_box(target) {
+        Some((l, t, r, b)) => (l.saturating_sub(1), t.saturating_sub(1), (r + 1).min(cols - 1), (b + 1).min(rows - 1)),
+        None => return Search::Found(target.to_vec()),
+    };
+    let width = right - left + 1;
+    let index = |x: usize, y: usize| {
+        if x >= left && x <= right && y >= top && y <= bottom {Some((y - top) * width + x - left)} else {None}
+    };
+
+    let cells: Vec<(usize, usize)> = (top..=bottom).flat_map(|y| (left..=right).map(move |x| (x, y))).collect();
+    let mut checks = vec![Vec::new(); cells.len()];
+    // the whole grid, as wrapping can put a cell's neighbors on the far side
+    for y in 0..rows {
+        for x in 0..cols {
+            let last = neighbors(x, y, cols, rows, edges)
+                .chain([(x, y)])
+                .filter_map(|(nx, ny)| index(nx, ny))
+                .max();
+            if let Some(last) = last {checks[last].push((x, y))}
+        }
+    }
+
+    let mut searcher = Searcher {
+        target, live, birth, edges, cells, checks,
+        pred: vec![vec![false; cols]; rows],
+        tries: 0,
+        limit,
+    };
+    searcher.search(&mut stop)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gen_grid, gol_step};
+
+    #[test]
+    fn predecessors_step_into_target() {
+        for edges in Edges::ALL {
+            // a blinker, which always has a predecessor away from the edges
+            let mut target = gen_grid(8, 7, None);
+            target[3][2..5].fill(true);
+            match predecessor(&target, 2, 3, edges, 1_000_000, || false) {
+                Search::Found(pred) => assert!(gol_step(&pred, 2, 3, edges) == target, "{} edges", edges),
+                _ => panic!("no predecessor found with {} edges", edges),
+            }
+            // up against the edge, where wrapping reaches the far side
+            let mut target = gen_grid(8, 7, None);
+            for row in &mut target[2..5] {row[0] = true}
+            if let Search::Found(pred) = predecessor(&target, 2, 3, edges, 1_000_000, || false) {
+                assert!(gol_step(&pred, 2, 3, edges) == target, "{} edges at the seam", edges);
+            }
+        }
+    }
+
+    #[test]
+    fn big_regions_and_stopping() {
+        // a blinker at each end of a long thin grid, so the region's thousands of cells deep
+        let mut target = gen_grid(7, 3000, None);
+        target[2][2..5].fill(true);
+        target[2997][2..5].fill(true);
+        match predecessor(&target, 2, 3, Edges::Dead, 10_000_000, || false) {
+            Search::Found(pred) => assert!(gol_step(&pred, 2, 3, Edges::Dead) == target),
+            _ => panic!("no predecessor found for two blinkers"),
+        }
+
+        // one that backs up through whole rows at a time, stopped long before the limit
+        let mut target = gen_grid(40, 40, None);
+        target[2][2..5].fill(true);
+        target[37][35..38].fill(true);
+        assert!(matches!(predecessor(&target, 2, 3, Edges::Dead, u64::MAX, || true), Search::Stopped));
+    }
+}
@@ -0,0 +1,73 @@
+// The grid and settings as they were on quitting, picked back up next time with
+// 'restore_session = yes' in the config. An RLE file beside the config, with the
+// settings in '#C session key value' lines.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::pattern::{self, Pattern};
+
+
+const PREFIX: &str = "#C session ";
+
+
+fn path() -> Option<PathBuf> {
+    Some(config::path()?.with_file_name("session.rle"))
+}
+
+
+pub fn save(pattern: &Pattern, settings: &[(&str, String)]) -> Result<(), String> {
+    write(&path().ok_or_else(|| "nowhere to keep the session".to_string())?, pattern, settings)
+}
+
+
+fn write(path: &Path, pattern: &Pattern, settings: &[(&str, String)]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let mut text: String = settings.iter().map(|(key, value)| format!("{}{} {}\n", PREFIX, key, value)).collect();
+    text += &pattern::to_rle(pattern);
+    std::fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+
+// None if there isn't one, or it can't be read
+pub fn load() -> Option<(Pattern, HashMap<String, String>)> {
+    read(&path()?)
+}
+
+
+// the settings lines are taken out of the comments, or they'd be saved into the user's own files
+fn read(path: &Path) -> Option<(Pattern, HashMap<String, String>)> {
+    let mut pattern = pattern::load(path).ok()?;
+    let settings = pattern.comments.iter()
+        .filter_map(|c| c.strip_prefix(PREFIX)?.split_once(' '))
+        .map(|(key, value)| (key.to_string(), value.trim().to_string()))
+        .collect();
+    pattern.comments.retain(|c| !c.starts_with(PREFIX));
+    Some((pattern, settings))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_stay_out_of_saves() {
+        let dir = std::env::temp_dir().join(format!("cursed_life_session_{}", std::process::id()));
+        let (session, saved) = (dir.join("session.rle"), dir.join("saved.rle"));
+        let mut pattern = Pattern::new(vec![vec![true; 3]], None);
+        pattern.comments.push("#C a note of the user's".to_string());
+        write(&session, &pattern, &[("edges", "torus".to_string())]).unwrap();
+
+        let (restored, settings) = read(&session).unwrap();
+        assert_eq!(settings.get("edges").map(String::as_str), Some("torus"));
+        pattern::save(&saved, &restored).unwrap();
+        let text = std::fs::read_to_string(&saved).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(!text.contains(PREFIX.trim_end()), "session lines saved:\n{}", text);
+        assert!(text.contains("#C a note of the user's"));
+    }
+}
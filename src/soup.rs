@@ -0,0 +1,308 @@
+// Headless soup search. Runs lots of random soups across all cores
+// and tallies how long they last and what they settle into.
+
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+
+use super::{gen_grid, gol_step, parse_rule, rule_str, stamp, Edges, Stepper, STEP_THREADS};
+use crate::generate;
+use crate::pattern::{self, Pattern};
+use crate::rng::{self, Rng};
+
+
+// longest period looked for when checking if a soup has settled
+pub const PERIOD_WINDOW: usize = 64;
+
+// how near the edge an object's taken off as having escaped, far enough
+// that the dead cells past it haven't touched it yet
+const RIM: usize = 2;
+// phases an object's looked at over when there's no period to go by, escaped or
+// left when the cap was hit. Enough for the common oscillators and spaceships.
+const UNKNOWN_PHASES: u64 = 4;
+
+// common objects, so the report can name them
+const NAMED: [(&str, &str); 13] = [
+    ("block", "2o$2o!"),
+    ("blinker", "3o!"),
+    ("beehive", "b2o$o2bo$b2o!"),
+    ("loaf", "b2o$o2bo$bobo$2bo!"),
+    ("boat", "2o$obo$bo!"),
+    ("ship", "2o$obo$b2o!"),
+    ("tub", "bo$obo$bo!"),
+    ("pond", "b2o$o2bo$o2bo$b2o!"),
+    ("long boat", "2o$obo$bobo$2bo!"),
+    ("barge", "bo$obo$bobo$2bo!"),
+    ("toad", "b3o$3o!"),
+    ("beacon", "2o$2o$2b2o$2b2o!"),
+    ("glider", "bo$2bo$3o!"),
+];
+
+
+pub struct SoupOptions {
+    count: usize,
+    size: usize,
+    live: i32,
+    birth: i32,
+    gens: u64,
+    seed: u64,
+    // blobs of value noise this many cells across, rather than uniform soup
+    noise: Option<f64>,
+    out: Option<PathBuf>,
+}
+
+// everything after 'soup' on the command line
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<SoupOptions, String> {
+    let mut options = SoupOptions {
+        count: 1000, size: 16, live: 2, birth: 3, gens: 5000, seed: rng::time_seed(), noise: None, out: None,
+    };
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        let bad = || format!("bad value '{}' for {}", value, arg);
+        match arg.as_str() {
+            "--count" => options.count = value.parse().map_err(|_| bad())?,
+            "--size" => options.size = value.parse().map_err(|_| bad())?,
+            "--gens" => options.gens = value.parse().map_err(|_| bad())?,
+            "--seed" => options.seed = value.parse().map_err(|_| bad())?,
+            "--noise" => options.noise = Some(value.parse().ok().filter(|s| *s >= 1.).ok_or_else(bad)?),
+            "--rule" => {
+                let (live, birth) = parse_rule(&value).ok_or_else(bad)?;
+                // every soup would fill in the empty space around it
+                if birth == 0 {return Err("soup doesn't support B0 rules".to_string())}
+                options.live = live;
+                options.birth = birth;
+            },
+            "-o" | "--out" => options.out = Some(PathBuf::from(value)),
+            _ => return Err(format!("unknown soup flag {}", arg)),
+        }
+    }
+    if options.size == 0 {return Err("--size must be at least 1".to_string())}
+    Ok(options)
+}
+
+
+// what one soup did
+struct SoupResult {
+    // generations until it started repeating, or the cap
+    lifetime: u64,
+    settled: bool,
+    objects: Vec<String>,
+}
+
+
+pub fn grid_hash(grid: &[Vec<bool>]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    grid.hash(&mut hasher);
+    hasher.finish()
+}
+
+
+// the 8 rotations/reflections of some cells
+fn orientations(cells: &[(isize, isize)]) -> Vec<Vec<(isize, isize)>> {
+    let mut result = Vec::new();
+    for swap in [false, true] {
+        for (sx, sy) in [(1, 1), (-1, 1), (1, -1), (-1, -1)] {
+            result.push(cells.iter().map(|&(x, y)| {
+                let (x, y) = if swap {(y, x)} else {(x, y)};
+                (x * sx, y * sy)
+            }).collect());
+        }
+    }
+    result
+}
+
+
+// lays loose cells out as a grid, shifted to the origin
+fn to_grid(cells: &[(isize, isize)]) -> Vec<Vec<bool>> {
+    let (min_x, min_y) = cells.iter().fold((isize::MAX, isize::MAX), |(mx, my), &(x, y)| (mx.min(x), my.min(y)));
+    let (max_x, max_y) = cells.iter().fold((isize::MIN, isize::MIN), |(mx, my), &(x, y)| (mx.max(x), my.max(y)));
+    let mut grid = vec![vec![false; (max_x - min_x + 1) as usize]; (max_y - min_y + 1) as usize];
+    for &(x, y) in cells {
+        grid[(y - min_y) as usize][(x - min_x) as usize] = true;
+    }
+    grid
+}
+
+
+// an RLE for an object that's the same whatever its orientation or phase.
+// Steps it in isolation for up to phases generations to see each phase.
+fn canonical(grid: &[Vec<bool>], phases: u64, live: i32, birth: i32) -> String {
+    // room to move around in while stepping
+    let margin = 2;
+    let mut field = gen_grid(grid[0].len() + margin * 2, grid.len() + margin * 2, None);
+    stamp(&mut field, grid, margin as isize, margin as isize);
+
+    let mut best: Option<String> = None;
+    for _ in 0..phases.max(1) {
+        let cells: Vec<(isize, isize)> = field.iter().enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().filter(|(_, c)| **c).map(move |(x, _)| (x as isize, y as isize)))
+            .collect();
+        if cells.is_empty() {break}
+        for cells in orientations(&cells) {
+            let rle = pattern::to_rle(&Pattern::new(to_grid(&cells), None));
+            // only the body, the header is just the size again
+            let body = rle.lines().skip(1).collect::<String>();
+            if best.as_ref().is_none_or(|b| body < *b) {best = Some(body)}
+        }
+        field = gol_step(&field, live, birth, Edges::Dead);
+    }
+    best.unwrap_or_default()
+}
+
+
+// splits a grid into 8-connected groups of live cells, where they are on it
+fn components(grid: &[Vec<bool>]) -> Vec<Vec<(isize, isize)>> {
+    let (rows, cols) = (grid.len(), grid[0].len());
+    let mut seen = vec![vec![false; cols]; rows];
+    let mut result = Vec::new();
+    for y in 0..rows {
+        for x in 0..cols {
+            if !grid[y][x] || seen[y][x] {continue}
+            let mut cells = Vec::new();
+            let mut stack = vec![(x, y)];
+            seen[y][x] = true;
+            while let Some((cx, cy)) = stack.pop() {
+                cells.push((cx as isize, cy as isize));
+                for ny in cy.saturating_sub(1)..=(cy + 1).min(rows - 1) {
+                    for nx in cx.saturating_sub(1)..=(cx + 1).min(cols - 1) {
+                        if grid[ny][nx] && !seen[ny][nx] {
+                            seen[ny][nx] = true;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+            }
+            result.push(cells);
+        }
+    }
+    result
+}
+
+
+// whether anything's within RIM cells of the edge
+fn at_rim(grid: &[Vec<bool>]) -> bool {
+    let (rows, cols) = (grid.len(), grid[0].len());
+    grid.iter().enumerate().any(|(y, row)| {
+        if y < RIM || y + RIM >= rows {row.contains(&true)}
+        else {row[..RIM].contains(&true) || row[cols - RIM..].contains(&true)}
+    })
+}
+
+
+fn run_soup(rng: &mut Rng, options: &SoupOptions) -> SoupResult {
+    // dead space around the soup for it to spread into, enough that most ash
+    // settles well inside it. Whatever reaches the rim
+    // is taken off and counted before the edge can bend it into something else.
+    let field = options.size * 9 + RIM * 2;
+    let mut grid = gen_grid(field, field, None);
+    let mut escaped = Vec::new();
+    // most of the field's empty, so only step around what's changing
+    let mut stepper = Stepper::new();
+    let soup = match options.noise {
+        Some(scale) => generate::noise(rng, options.size, options.size, scale),
+        None => generate::uniform(rng, options.size, options.size, 0.5),
+    };
+    stamp(&mut grid, &soup, (options.size * 4 + RIM) as isize, (options.size * 4 + RIM) as isize);
+
+    let mut recent: VecDeque<u64> = VecDeque::with_capacity(PERIOD_WINDOW);
+    let mut period = None;
+    let mut generation = 0;
+    while generation < options.gens {
+        let hash = grid_hash(&grid);
+        if let Some(i) = recent.iter().rev().position(|h| *h == hash) {
+            period = Some(i as u64 + 1);
+            break
+        }
+        if recent.len() == PERIOD_WINDOW {recent.pop_front();}
+        recent.push_back(hash);
+        grid = stepper.step(&grid, options.live, options.birth, Edges::Dead, generation);
+        generation += 1;
+        if at_rim(&grid) {
+            for cells in components(&grid) {
+                let rim = cells.iter().any(|&(x, y)| {
+                    x.min(y) < RIM as isize || x.max(y) + RIM as isize >= field as isize
+                });
+                if !rim {continue}
+                for &(x, y) in &cells {
+                    grid[y as usize][x as usize] = false;
+                    stepper.touch(x as usize, y as usize);
+                }
+                escaped.push(canonical(&to_grid(&cells), UNKNOWN_PHASES, options.live, options.birth));
+            }
+        }
+    }
+
+    let phases = period.unwrap_or(UNKNOWN_PHASES);
+    SoupResult {
+        lifetime: generation - period.unwrap_or(0),
+        settled: period.is_some(),
+        objects: components(&grid).iter()
+            .map(|c| canonical(&to_grid(c), phases, options.live, options.birth))
+            .chain(escaped)
+            .collect(),
+    }
+}
+
+
+pub fn run(options: SoupOptions) -> std::io::Result<()> {
+    // soups already fill every core, each one stepping on its own
+    STEP_THREADS.store(1, Ordering::Relaxed);
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get()).min(options.count.max(1));
+    let results: Vec<SoupResult> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads).map(|t| {
+            let options = &options;
+            scope.spawn(move || {
+                let mut rng = Rng::new(options.seed.wrapping_add(t as u64));
+                // soups t, t + threads, t + threads*2...
+                (t..options.count).step_by(threads).map(|_| run_soup(&mut rng, options)).collect::<Vec<_>>()
+            })
+        }).collect();
+        handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+    });
+
+    let names: HashMap<String, &str> = NAMED.iter().map(|(name, body)| {
+        let cells = pattern::parse_rle(&format!("x = 0, y = 0\n{}", body)).unwrap().cells;
+        (canonical(&cells, 4, 2, 3), *name)
+    }).collect();
+    // names only hold under Conway's rules
+    let conway = (options.live, options.birth) == (2, 3);
+
+    let mut census: HashMap<String, usize> = HashMap::new();
+    for object in results.iter().flat_map(|r| r.objects.iter()) {
+        let name = match names.get(object) {
+            Some(name) if conway => name.to_string(),
+            _ => object.clone(),
+        };
+        *census.entry(name).or_insert(0) += 1;
+    }
+    let mut census: Vec<(String, usize)> = census.into_iter().collect();
+    census.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut lifetimes: Vec<u64> = results.iter().map(|r| r.lifetime).collect();
+    lifetimes.sort_unstable();
+    let settled = results.iter().filter(|r| r.settled).count();
+
+    let mut report = String::new();
+    report += &format!("soups: {}  size: {}x{}  rule: {}  seed: {}",
+                       options.count, options.size, options.size, rule_str(options.live, options.birth), options.seed);
+    if let Some(scale) = options.noise {report += &format!("  noise: {}", scale)}
+    report.push('\n');
+    report += &format!("settled: {}  hit the {} generation cap: {}\n", settled, options.gens, options.count - settled);
+    if !lifetimes.is_empty() {
+        report += &format!("lifetime: mean {:.1}  median {}  max {}\n",
+                           lifetimes.iter().sum::<u64>() as f64 / lifetimes.len() as f64,
+                           lifetimes[lifetimes.len() / 2], lifetimes[lifetimes.len() - 1]);
+    }
+    report += "\nobjects:\n";
+    for (name, count) in census {
+        report += &format!("{:>8}  {}\n", count, name);
+    }
+
+    match &options.out {
+        Some(path) => std::fs::write(path, report),
+        None => std::io::stdout().write_all(report.as_bytes()),
+    }
+}
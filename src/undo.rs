@@ -0,0 +1,393 @@
+// Changes to the grid as a tree, for undo and redo. Undoing and then changing something
+// starts a branch beside the old one rather than losing it, and any point on the tree
+// can be gone back to. Each point keeps the cells that flipped since the one before it,
+// and only the grids we're at are kept whole. There's one grid per layer.
+
+// a cell that flipped, as the layer it's on then x, y
+type Flip = (u8, u16, u16);
+
+// text pinned to a cell, as x, y and the text
+pub type Label = (usize, usize, String);
+
+struct Node {
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // the child redo goes down to, whichever was last come back up from
+    redo: Option<usize>,
+    // the cells that flipped since the parent, sorted
+    delta: Vec<Flip>,
+    // all the labels at this point, as they're few and small
+    labels: Vec<Label>,
+    // what made the change, and the generation it left the grid at
+    label: String,
+    generation: u64,
+}
+
+pub struct UndoTree {
+    // dropped nodes leave a hole so the rest keep their numbers
+    nodes: Vec<Option<Node>>,
+    root: usize,
+    current: usize,
+    // each layer's grid at current
+    state: Vec<Vec<Vec<bool>>>,
+    // whether the last thing done was a change, so more of the same can join it
+    extending: bool,
+    bytes: usize,
+    cap: usize,
+}
+
+fn node_bytes(delta: &[Flip], labels: &[Label]) -> usize {
+    std::mem::size_of::<Node>() + std::mem::size_of_val(delta)
+        + labels.iter().map(|label| std::mem::size_of_val(label) + label.2.len()).sum::<usize>()
+}
+
+// the cells that differ, in the order they're sorted by
+fn diff(old: &[Vec<Vec<bool>>], new: &[&[Vec<bool>]]) -> Vec<Flip> {
+    let mut delta: Vec<Flip> = old.iter().zip(new).enumerate().flat_map(|(layer, (old, new))| {
+        old.iter().zip(new.iter()).enumerate().flat_map(move |(y, (old_row, new_row))| {
+            old_row.iter().zip(new_row).enumerate()
+                .filter(|(_, (a, b))| a != b)
+                .map(move |(x, _)| (layer as u8, x as u16, y as u16))
+        })
+    }).collect();
+    delta.sort_unstable();
+    delta
+}
+
+// the cells in one or other but not both, for two changes made one after the other
+fn combine(a: &[Flip], b: &[Flip]) -> Vec<Flip> {
+    let (mut a, mut b) = (a.iter().peekable(), b.iter().peekable());
+    let mut both = Vec::new();
+    loop {
+        match (a.peek(), b.peek()) {
+            (Some(x), Some(y)) if x == y => {a.next(); b.next();},
+            (Some(x), Some(y)) if x < y => both.push(*a.next().unwrap()),
+            (Some(_), Some(_)) => both.push(*b.next().unwrap()),
+            (Some(_), None) => both.push(*a.next().unwrap()),
+            (None, Some(_)) => both.push(*b.next().unwrap()),
+            (None, None) => break both,
+        }
+    }
+}
+
+fn flip(grids: &mut [Vec<Vec<bool>>], delta: &[Flip]) {
+    for &(layer, x, y) in delta {
+        let cell = &mut grids[layer as usize][y as usize][x as usize];
+        *cell = !*cell;
+    }
+}
+
+// columns and rows of each grid
+fn sizes<G: AsRef<[Vec<bool>]>>(grids: &[G]) -> Vec<(usize, usize)> {
+    grids.iter().map(|grid| {
+        let grid = grid.as_ref();
+        (grid.first().map_or(0, |row| row.len()), grid.len())
+    }).collect()
+}
+
+impl UndoTree {
+    pub fn new(grids: &[&[Vec<bool>]], labels: &[Label], generation: u64, cap: usize) -> Self {
+        let mut tree = UndoTree {nodes: Vec::new(), root: 0, current: 0, state: Vec::new(), extending: false, bytes: 0, cap};
+        tree.reset(grids, labels, generation);
+        tree
+    }
+
+    // forget everything, starting again from these grids
+    pub fn reset(&mut self, grids: &[&[Vec<bool>]], labels: &[Label], generation: u64) {
+        let root = Node {
+            parent: None, children: Vec::new(), redo: None, delta: Vec::new(), labels: labels.to_vec(),
+            label: "start".to_string(), generation,
+        };
+        self.bytes = node_bytes(&root.delta, &root.labels);
+        self.nodes = vec![Some(root)];
+        self.root = 0;
+        self.current = 0;
+        self.state = grids.iter().map(|grid| grid.to_vec()).collect();
+        self.extending = false;
+    }
+
+    pub fn set_cap(&mut self, cap: usize) {
+        self.cap = cap;
+        self.evict();
+    }
+
+    // roughly what's kept, as counted against the cap
+    pub fn bytes(&self) -> usize {
+        self.bytes + self.state.iter()
+            .map(|grid| std::mem::size_of_val(grid.as_slice()) + grid.iter().map(|row| row.len()).sum::<usize>())
+            .sum::<usize>()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.iter().flatten().count()
+    }
+
+    // each layer's grid at the point we're at, after an undo, redo or jump
+    pub fn state(&self) -> &[Vec<Vec<bool>>] {
+        &self.state
+    }
+
+    pub fn labels(&self) -> &[Label] {
+        &self.node(self.current).labels
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    fn node(&self, id: usize) -> &Node {
+        self.nodes[id].as_ref().expect("tree links only point at kept nodes")
+    }
+
+    fn node_mut(&mut self, id: usize) -> &mut Node {
+        self.nodes[id].as_mut().expect("tree links only point at kept nodes")
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.node(self.current).generation
+    }
+
+    // fit resized grids, forgetting what happened to cells that are no longer on them
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        for grid in &mut self.state {
+            for row in grid.iter_mut() {row.resize(cols, false)}
+            grid.resize(rows, vec![false; cols]);
+        }
+        self.bytes = 0;
+        for node in self.nodes.iter_mut().flatten() {
+            node.delta.retain(|&(_, x, y)| (x as usize) < cols && (y as usize) < rows);
+            self.bytes += node_bytes(&node.delta, &node.labels);
+        }
+    }
+
+    // remember the grids and labels as they are now, if they've changed. With extend set it
+    // joins the last change instead when that had the same label and nothing's been undone
+    // since, so a stroke or a run of steps comes back off in one go. A layer added or taken
+    // away starts the tree again.
+    pub fn record(&mut self, grids: &[&[Vec<bool>]], labels: &[Label], generation: u64, label: &str, extend: bool) {
+        if sizes(grids) != sizes(&self.state) {
+            self.reset(grids, labels, generation);
+            return
+        }
+        let delta = diff(&self.state, grids);
+        if delta.is_empty() && generation == self.generation() && labels == self.labels() {return}
+        // the change takes state to the grids, without copying them whole
+        flip(&mut self.state, &delta);
+        let current = self.current;
+        let joins = extend && self.extending && current != self.root && self.node(current).children.is_empty()
+            && self.node(current).label == label;
+        if joins {
+            let node = self.node_mut(current);
+            let old = node_bytes(&node.delta, &node.labels);
+            node.delta = combine(&node.delta, &delta);
+            node.labels = labels.to_vec();
+            node.generation = generation;
+            let new = node_bytes(&node.delta, &node.labels);
+            self.bytes = self.bytes + new - old;
+        } else {
+            self.bytes += node_bytes(&delta, labels);
+            let id = self.nodes.len();
+            self.nodes.push(Some(Node {
+                parent: Some(current), children: Vec::new(), redo: None, delta, labels: labels.to_vec(),
+                label: label.to_string(), generation,
+            }));
+            let parent = self.node_mut(current);
+            parent.children.push(id);
+            parent.redo = Some(id);
+            self.current = id;
+        }
+        self.extending = true;
+        self.evict();
+    }
+
+    // back up to the parent. false at the root.
+    pub fn undo(&mut self) -> bool {
+        let current = self.current;
+        let parent = match self.node(current).parent {
+            Some(parent) => parent,
+            None => return false,
+        };
+        flip(&mut self.state, &self.nodes[current].as_ref().unwrap().delta);
+        self.node_mut(parent).redo = Some(current);
+        self.current = parent;
+        self.extending = false;
+        true
+    }
+
+    // down to the child last undone from, or the newest one. false at a leaf.
+    pub fn redo(&mut self) -> bool {
+        let child = match self.node(self.current).redo {
+            Some(child) => child,
+            None => return false,
+        };
+        flip(&mut self.state, &self.nodes[child].as_ref().unwrap().delta);
+        self.current = child;
+        self.extending = false;
+        true
+    }
+
+    // go anywhere on the tree, up to where the way there branches off and down from it
+    pub fn jump(&mut self, target: usize) {
+        if self.nodes.get(target).is_none_or(|n| n.is_none()) {return}
+        let mut down = Vec::new();
+        let mut at = target;
+        while !self.is_ancestor(at, self.current) {
+            down.push(at);
+            at = self.node(at).parent.expect("the root is everything's ancestor");
+        }
+        while self.current != at {self.undo();}
+        for id in down.into_iter().rev() {
+            self.node_mut(self.current).redo = Some(id);
+            self.redo();
+        }
+    }
+
+    fn is_ancestor(&self, ancestor: usize, mut id: usize) -> bool {
+        loop {
+            if id == ancestor {return true}
+            match self.node(id).parent {
+                Some(parent) => id = parent,
+                None => return false,
+            }
+        }
+    }
+
+    // drop the oldest leaves off other branches until back under the cap, then the
+    // oldest points on the way to here
+    fn evict(&mut self) {
+        while self.bytes > self.cap {
+            let leaf = self.nodes.iter().enumerate()
+                .find(|(id, node)| *id != self.current && node.as_ref().is_some_and(|n| n.children.is_empty()))
+                .map(|(id, _)| id);
+            match leaf {
+                Some(id) => {
+                    let node = self.nodes[id].take().unwrap();
+                    self.bytes -= node_bytes(&node.delta, &node.labels);
+                    let parent = self.node_mut(node.parent.expect("the root has children"));
+                    parent.children.retain(|c| *c != id);
+                    if parent.redo == Some(id) {parent.redo = parent.children.last().copied()}
+                },
+                // just a line from the root to here
+                None if self.root != self.current => {
+                    let root = self.nodes[self.root].take().unwrap();
+                    self.bytes -= node_bytes(&root.delta, &root.labels);
+                    self.root = root.children[0];
+                    // its delta was from a grid that's gone
+                    let node = self.node_mut(root.children[0]);
+                    node.parent = None;
+                    let freed = std::mem::size_of_val(std::mem::take(&mut node.delta).as_slice());
+                    self.bytes -= freed;
+                },
+                None => break,
+            }
+        }
+    }
+
+    // a line a node, drawn as a tree. A point with more than one way on from it has each
+    // branch indented under it, otherwise they're kept in a column.
+    pub fn lines(&self) -> Vec<(usize, String)> {
+        let mut lines = Vec::new();
+        self.draw(self.root, String::new(), String::new(), &mut lines);
+        lines
+    }
+
+    fn draw(&self, mut id: usize, mut first: String, rest: String, lines: &mut Vec<(usize, String)>) {
+        loop {
+            let node = self.node(id);
+            let cells = node.delta.len();
+            let size = if node.parent.is_none() {String::new()} else {format!(", {} cell{}", cells, if cells == 1 {""} else {"s"})};
+            lines.push((id, format!("{}{} gen {}{}", first, node.label, node.generation, size)));
+            match node.children.as_slice() {
+                [only] => {
+                    id = *only;
+                    first = rest.clone();
+                },
+                children => {
+                    for (i, child) in children.iter().enumerate() {
+                        let last = i + 1 == children.len();
+                        self.draw(*child, format!("{}{}", rest, if last {"└ "} else {"├ "}),
+                                  format!("{}{}", rest, if last {"  "} else {"│ "}), lines);
+                    }
+                    return
+                },
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(cells: &[(usize, usize)]) -> Vec<Vec<bool>> {
+        let mut grid = vec![vec![false; 4]; 3];
+        for &(x, y) in cells {grid[y][x] = true}
+        grid
+    }
+
+    #[test]
+    fn combine_keeps_cells_flipped_once() {
+        assert_eq!(combine(&[(0, 0, 0), (0, 2, 1), (1, 0, 0)], &[(0, 1, 0), (0, 2, 1)]), vec![(0, 0, 0), (0, 1, 0), (1, 0, 0)]);
+        assert_eq!(combine(&[], &[(0, 3, 2)]), vec![(0, 3, 2)]);
+        assert!(combine(&[(0, 3, 2)], &[(0, 3, 2)]).is_empty());
+    }
+
+    #[test]
+    fn runs_join_and_branches_keep() {
+        let (a, b, c) = (grid(&[]), grid(&[(1, 1)]), grid(&[(1, 1), (2, 1)]));
+        let mut tree = UndoTree::new(&[&a], &[], 0, usize::MAX);
+        tree.record(&[&b], &[], 1, "run", true);
+        tree.record(&[&c], &[], 2, "run", true);
+        // both steps came off in one
+        assert_eq!(tree.len(), 2);
+        assert!(tree.undo());
+        assert_eq!(tree.state(), std::slice::from_ref(&a));
+        assert_eq!(tree.generation(), 0);
+        // a change after undoing branches off beside the run
+        let label = vec![(0, 0, "hi".to_string())];
+        tree.record(&[&a], &label, 0, "N", false);
+        assert_eq!(tree.len(), 3);
+        assert!(!tree.redo());
+        assert!(tree.undo());
+        assert!(tree.labels().is_empty());
+        assert!(tree.redo());
+        assert_eq!(tree.labels(), label.as_slice());
+    }
+
+    #[test]
+    fn jumps_across_branches() {
+        let (a, b, c) = (grid(&[]), grid(&[(0, 0)]), grid(&[(3, 2)]));
+        let layer = grid(&[(2, 2)]);
+        let mut tree = UndoTree::new(&[&a, &a], &[], 0, usize::MAX);
+        tree.record(&[&b, &layer], &[], 1, "e", false);
+        let first = tree.current();
+        tree.undo();
+        tree.record(&[&c, &a], &[], 1, "e", false);
+        tree.jump(first);
+        assert_eq!(tree.state(), [b, layer]);
+        tree.jump(0);
+        assert_eq!(tree.state(), [a.clone(), a]);
+    }
+
+    #[test]
+    fn evicts_other_branches_first() {
+        let (a, b, c, d) = (grid(&[]), grid(&[(0, 0)]), grid(&[(1, 0)]), grid(&[(1, 0), (2, 0)]));
+        let mut tree = UndoTree::new(&[&a], &[], 0, usize::MAX);
+        tree.record(&[&b], &[], 1, "x", false);
+        tree.undo();
+        tree.record(&[&c], &[], 1, "y", false);
+        tree.record(&[&d], &[], 2, "z", false);
+        assert_eq!(tree.len(), 4);
+        // room for the line here only, so the other branch goes
+        tree.set_cap(tree.bytes - 1);
+        assert_eq!(tree.len(), 3);
+        assert!(tree.lines().iter().all(|(_, line)| !line.starts_with("x")));
+        // then the oldest points on the way here, never the one we're at
+        tree.set_cap(0);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree.state(), [d]);
+        assert!(!tree.undo());
+    }
+}
+